@@ -0,0 +1,609 @@
+use crate::backend::{
+    BackendCapabilities, BackendChatRequest, BackendChatResponse, BackendKind, ChatBackend,
+    ChunkCallback, StreamChunk,
+};
+use crate::models::{
+    Attachment, Message, MessageRole, OllamaModel, OllamaModelDetails, ToolCall, ToolCallStatus,
+};
+use async_trait::async_trait;
+use base64::Engine;
+use futures::StreamExt;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// The Messages API requires `max_tokens`; used when the conversation sets none
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+const ERROR_BODY_LIMIT: usize = 280;
+
+/// Client for the Anthropic Messages API
+#[derive(Clone)]
+pub struct AnthropicClient {
+    client: Client,
+    /// API root including the version segment, e.g. `https://api.anthropic.com/v1`
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: Option<u32>,
+    #[serde(default)]
+    output_tokens: Option<u32>,
+}
+
+/// Tool use block assembled from streamed `input_json_delta` fragments
+#[derive(Debug, Default)]
+struct PartialToolUse {
+    id: String,
+    name: String,
+    input_json: String,
+}
+
+fn trim_error_body(body: &str) -> String {
+    let trimmed = body.trim();
+    let mut truncated = trimmed.chars().take(ERROR_BODY_LIMIT).collect::<String>();
+    if trimmed.chars().count() > ERROR_BODY_LIMIT {
+        truncated.push('…');
+    }
+    truncated
+}
+
+fn extract_error_message(payload: &Value) -> Option<String> {
+    payload
+        .pointer("/error/message")
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+}
+
+/// Content blocks for a user message: attachments first, then the text
+fn attachment_blocks(attachments: &[Attachment]) -> Vec<Value> {
+    attachments
+        .iter()
+        .filter_map(|attachment| {
+            let mime_type = attachment.mime_type.as_str();
+            if mime_type.starts_with("image/") {
+                Some(json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": mime_type,
+                        "data": base64::engine::general_purpose::STANDARD.encode(&attachment.data),
+                    },
+                }))
+            } else if mime_type == "application/pdf" {
+                Some(json!({
+                    "type": "document",
+                    "title": attachment.name,
+                    "source": {
+                        "type": "base64",
+                        "media_type": "application/pdf",
+                        "data": base64::engine::general_purpose::STANDARD.encode(&attachment.data),
+                    },
+                }))
+            } else if mime_type.starts_with("text/") {
+                Some(json!({
+                    "type": "document",
+                    "title": attachment.name,
+                    "source": {
+                        "type": "text",
+                        "media_type": "text/plain",
+                        "data": String::from_utf8_lossy(&attachment.data),
+                    },
+                }))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn text_block(text: &str) -> Option<Value> {
+    (!text.trim().is_empty()).then(|| json!({ "type": "text", "text": text }))
+}
+
+/// Convert app messages to Messages API format.
+///
+/// System messages are folded into the top-level system prompt, tool results
+/// become `tool_result` blocks in a user turn, and consecutive turns with the
+/// same role are merged because the API requires alternating roles.
+fn messages_to_anthropic(
+    messages: &[Message],
+    system_prompt: Option<&str>,
+) -> (Option<String>, Vec<Value>) {
+    let mut system_parts: Vec<&str> = system_prompt
+        .map(str::trim)
+        .filter(|prompt| !prompt.is_empty())
+        .into_iter()
+        .collect();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message.role {
+            MessageRole::System => {
+                if !message.content.trim().is_empty() {
+                    system_parts.push(message.content.trim());
+                }
+                continue;
+            }
+            MessageRole::User => {
+                let mut blocks = attachment_blocks(&message.attachments);
+                blocks.extend(text_block(&message.content));
+                ("user", blocks)
+            }
+            MessageRole::Assistant => {
+                let mut blocks: Vec<Value> = text_block(&message.content).into_iter().collect();
+                blocks.extend(message.tool_calls.iter().map(|call| {
+                    json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.tool_name,
+                        "input": call.arguments,
+                    })
+                }));
+                ("assistant", blocks)
+            }
+            MessageRole::Tool => {
+                let blocks = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "type": "tool_result",
+                            "tool_use_id": call.id,
+                            "content": message.content,
+                            "is_error": call.status == ToolCallStatus::Failed,
+                        })
+                    })
+                    .collect();
+                ("user", blocks)
+            }
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+
+    let system = (!system_parts.is_empty()).then(|| system_parts.join("\n\n"));
+    let messages = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+
+    (system, messages)
+}
+
+fn to_tool_call(id: String, name: String, input: Value) -> ToolCall {
+    ToolCall {
+        id,
+        tool_name: name,
+        arguments: if input.is_null() { json!({}) } else { input },
+        status: ToolCallStatus::Pending,
+        result: None,
+        error_message: None,
+        execution_time_ms: None,
+    }
+}
+
+fn to_ollama_model(model: ModelEntry) -> OllamaModel {
+    OllamaModel {
+        name: model.id,
+        modified_at: model.created_at,
+        size: None,
+        digest: None,
+        details: Some(OllamaModelDetails {
+            format: Some("anthropic".to_string()),
+            family: Some("claude".to_string()),
+            families: None,
+            parameter_size: None,
+            quantization_level: model.display_name,
+        }),
+        connection_id: None,
+    }
+}
+
+/// Apply one server-sent event to the response being assembled
+fn process_stream_event(
+    event_name: &str,
+    data: &str,
+    result: &mut BackendChatResponse,
+    tool_uses: &mut BTreeMap<u64, PartialToolUse>,
+    on_chunk: &mut ChunkCallback<'_>,
+) -> Result<(), String> {
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let payload: Value = serde_json::from_str(data)
+        .map_err(|error| format!("Failed to parse Anthropic stream payload: {}", error))?;
+    let index = payload.get("index").and_then(|value| value.as_u64());
+
+    match event_name {
+        "message_start" => {
+            if let Some(input_tokens) = payload
+                .pointer("/message/usage/input_tokens")
+                .and_then(|value| value.as_u64())
+            {
+                result.prompt_eval_count = Some(input_tokens as u32);
+            }
+        }
+        "content_block_start" => {
+            let block = payload.get("content_block").unwrap_or(&Value::Null);
+            if let (Some(index), Some("tool_use")) =
+                (index, block.get("type").and_then(|value| value.as_str()))
+            {
+                tool_uses.insert(
+                    index,
+                    PartialToolUse {
+                        id: block
+                            .get("id")
+                            .and_then(|value| value.as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        name: block
+                            .get("name")
+                            .and_then(|value| value.as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        input_json: String::new(),
+                    },
+                );
+            }
+        }
+        "content_block_delta" => {
+            let delta = payload.get("delta").unwrap_or(&Value::Null);
+            match delta.get("type").and_then(|value| value.as_str()) {
+                Some("text_delta") => {
+                    if let Some(text) = delta
+                        .get("text")
+                        .and_then(|value| value.as_str())
+                        .filter(|text| !text.is_empty())
+                    {
+                        result.content.push_str(text);
+                        on_chunk(StreamChunk::Content(text));
+                    }
+                }
+                Some("input_json_delta") => {
+                    if let (Some(tool_use), Some(partial_json)) = (
+                        index.and_then(|index| tool_uses.get_mut(&index)),
+                        delta.get("partial_json").and_then(|value| value.as_str()),
+                    ) {
+                        tool_use.input_json.push_str(partial_json);
+                    }
+                }
+                _ => {}
+            }
+        }
+        "message_delta" => {
+            if let Some(output_tokens) = payload
+                .pointer("/usage/output_tokens")
+                .and_then(|value| value.as_u64())
+            {
+                result.eval_count = Some(output_tokens as u32);
+            }
+        }
+        "error" => {
+            return Err(extract_error_message(&payload)
+                .unwrap_or_else(|| "Anthropic returned a streaming error".to_string()));
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+impl AnthropicClient {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty()),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let request = self
+            .client
+            .request(method, url)
+            .header("anthropic-version", ANTHROPIC_VERSION);
+
+        if let Some(api_key) = &self.api_key {
+            request.header("x-api-key", api_key)
+        } else {
+            request
+        }
+    }
+
+    fn transport_error(&self, action: &str, error: reqwest::Error) -> String {
+        if error.is_timeout() {
+            return format!("{action} timed out while contacting Anthropic");
+        }
+        if error.is_connect() {
+            return format!(
+                "{action} could not connect to Anthropic at {}",
+                self.base_url
+            );
+        }
+
+        format!("{action} failed: {error}")
+    }
+
+    fn status_error(&self, action: &str, status: StatusCode, body: &str) -> String {
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return "Anthropic authentication failed. Check the configured API key.".to_string();
+        }
+
+        let detail = serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|payload| extract_error_message(&payload))
+            .unwrap_or_else(|| trim_error_body(body));
+        if detail.is_empty() {
+            return format!("{action} failed with status: {status}");
+        }
+
+        format!("{action} failed ({status}): {detail}")
+    }
+
+    fn messages_body(&self, request: &BackendChatRequest, stream: bool) -> Value {
+        let (system, messages) =
+            messages_to_anthropic(&request.messages, request.system_prompt.as_deref());
+        let parameters = &request.parameters;
+
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages,
+            "max_tokens": parameters.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "temperature": parameters.temperature.clamp(0.0, 1.0),
+            "stream": stream,
+        });
+
+        if let Some(system) = system {
+            body["system"] = json!(system);
+        }
+        if let Some(top_k) = parameters.top_k {
+            body["top_k"] = json!(top_k);
+        }
+        if let Some(stop) = parameters.stop_sequences() {
+            body["stop_sequences"] = json!(stop);
+        }
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters,
+                    })
+                })
+                .collect();
+            body["tools"] = Value::Array(tools);
+        }
+
+        body
+    }
+
+    async fn send_messages(&self, action: &str, body: &Value) -> Result<reqwest::Response, String> {
+        let response = self
+            .request(Method::POST, "/messages")
+            .json(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(action, error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error(action, status, &body));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl ChatBackend for AnthropicClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Anthropic
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: true,
+            model_details: false,
+            model_management: false,
+            sessions: false,
+            tools: true,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        ChatBackend::list_models(self).await.map(|_| true)
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let response = self
+            .request(Method::GET, "/models?limit=1000")
+            .send()
+            .await
+            .map_err(|error| self.transport_error("Anthropic model listing", error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error("Anthropic model listing", status, &body));
+        }
+
+        let models: ModelsResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse Anthropic model list: {}", error))?;
+
+        Ok(models.data.into_iter().map(to_ollama_model).collect())
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let body = self.messages_body(request, false);
+        let response = self.send_messages("Anthropic chat request", &body).await?;
+
+        let message: MessagesResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse Anthropic response: {}", error))?;
+
+        let usage = message.usage;
+        let mut result = BackendChatResponse {
+            prompt_eval_count: usage.as_ref().and_then(|usage| usage.input_tokens),
+            eval_count: usage.as_ref().and_then(|usage| usage.output_tokens),
+            ..BackendChatResponse::default()
+        };
+        for block in message.content {
+            match block {
+                ContentBlock::Text { text } => result.content.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => {
+                    result.tool_calls.push(to_tool_call(id, name, input))
+                }
+                ContentBlock::Other => {}
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let body = self.messages_body(request, true);
+        let response = self
+            .send_messages("Anthropic streaming chat request", &body)
+            .await?;
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut current_event = "message".to_string();
+        let mut data_buffer = String::new();
+        let mut result = BackendChatResponse::default();
+        let mut tool_uses: BTreeMap<u64, PartialToolUse> = BTreeMap::new();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|error| format!("Anthropic stream error: {}", error))?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(newline_pos) = buffer.find('\n') {
+                let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
+                buffer = buffer[newline_pos + 1..].to_string();
+
+                if line.is_empty() {
+                    process_stream_event(
+                        &current_event,
+                        data_buffer.trim(),
+                        &mut result,
+                        &mut tool_uses,
+                        on_chunk,
+                    )?;
+                    current_event = "message".to_string();
+                    data_buffer.clear();
+                    continue;
+                }
+
+                if let Some(event_name) = line.strip_prefix("event:") {
+                    current_event = event_name.trim().to_string();
+                    continue;
+                }
+
+                if let Some(data_line) = line.strip_prefix("data:") {
+                    if !data_buffer.is_empty() {
+                        data_buffer.push('\n');
+                    }
+                    data_buffer.push_str(data_line.trim());
+                }
+            }
+        }
+
+        if !data_buffer.trim().is_empty() {
+            process_stream_event(
+                &current_event,
+                data_buffer.trim(),
+                &mut result,
+                &mut tool_uses,
+                on_chunk,
+            )?;
+        }
+
+        result.tool_calls = tool_uses
+            .into_values()
+            .map(|tool_use| {
+                let input = if tool_use.input_json.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&tool_use.input_json)
+                        .unwrap_or(Value::String(tool_use.input_json))
+                };
+                to_tool_call(tool_use.id, tool_use.name, input)
+            })
+            .collect();
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,695 @@
+use crate::anthropic::AnthropicClient;
+use crate::gemini::GeminiClient;
+use crate::lm_studio::LmStudioClient;
+use crate::models::{
+    Attachment, Connection, Message, MessageRole, ModelCreateRequest, ModelParameters, OllamaModel,
+    OllamaRunningModel, ToolCall,
+};
+use crate::ollama::OllamaClient;
+use crate::openai_compat::OpenAiCompatClient;
+use crate::opencode::OpencodeClient;
+use crate::structured::ResponseFormat;
+use crate::tools::ToolDefinition;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BackendKind {
+    Ollama,
+    Opencode,
+    LmStudio,
+    OpenAiCompat,
+    Anthropic,
+    Gemini,
+}
+
+impl BackendKind {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_lowercase())
+            .as_deref()
+        {
+            Some("opencode") => Some(Self::Opencode),
+            Some("ollama") => Some(Self::Ollama),
+            Some("lmstudio") | Some("lm-studio") | Some("lm_studio") => Some(Self::LmStudio),
+            Some("openai") | Some("openai-compatible") | Some("openai_compat") => {
+                Some(Self::OpenAiCompat)
+            }
+            Some("anthropic") => Some(Self::Anthropic),
+            Some("gemini") | Some("google") => Some(Self::Gemini),
+            _ => None,
+        }
+    }
+
+    /// Identifier used for `Connection.backend` and the frontend `BackendType`
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Ollama => "ollama",
+            Self::Opencode => "opencode",
+            Self::LmStudio => "lmstudio",
+            Self::OpenAiCompat => "openai",
+            Self::Anthropic => "anthropic",
+            Self::Gemini => "gemini",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ollama => "Ollama",
+            Self::Opencode => "OpenCode",
+            Self::LmStudio => "LM Studio",
+            Self::OpenAiCompat => "OpenAI-compatible",
+            Self::Anthropic => "Anthropic",
+            Self::Gemini => "Google Gemini",
+        }
+    }
+}
+
+/// Optional features a backend supports on top of plain chat
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendCapabilities {
+    pub streaming: bool,
+    pub vision: bool,
+    pub model_details: bool,
+    pub model_management: bool,
+    pub sessions: bool,
+    pub tools: bool,
+}
+
+/// Backend-agnostic chat request built by the commands layer
+#[derive(Debug, Clone)]
+pub struct BackendChatRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub system_prompt: Option<String>,
+    pub parameters: ModelParameters,
+    pub session_id: Option<String>,
+    /// Tools the model may call; empty when tool calling is off
+    pub tools: Vec<ToolDefinition>,
+    /// Ask thinking models to reason before answering; `None` keeps the
+    /// model's default
+    pub think: Option<bool>,
+    /// How long a local server keeps the model loaded afterwards
+    pub keep_alive: Option<String>,
+    /// Constrain the response to JSON, where the backend supports it
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Backend-agnostic chat response
+#[derive(Debug, Clone, Default)]
+pub struct BackendChatResponse {
+    pub content: String,
+    /// Reasoning the model produced before its answer, kept out of `content`
+    pub reasoning: Option<String>,
+    pub session_id: Option<String>,
+    /// Prompt (input) tokens processed for this response
+    pub prompt_eval_count: Option<u32>,
+    /// Completion (output) tokens generated for this response
+    pub eval_count: Option<u32>,
+    pub total_duration: Option<u64>,
+    /// Cost in USD when the backend reports it itself
+    pub cost: Option<f64>,
+    /// Tool calls requested by the model in this turn
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl BackendChatResponse {
+    /// Add the token counts and cost of another response, e.g. an earlier
+    /// round of a tool-calling exchange
+    pub fn add_usage(&mut self, other: &BackendChatResponse) {
+        fn sum<T: std::ops::Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            }
+        }
+
+        self.prompt_eval_count = sum(self.prompt_eval_count, other.prompt_eval_count);
+        self.eval_count = sum(self.eval_count, other.eval_count);
+        self.cost = sum(self.cost, other.cost);
+    }
+}
+
+/// A conversation held by a backend that keeps its own sessions
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendSession {
+    pub id: String,
+    pub title: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Session this one was forked from or started by
+    pub parent_id: Option<String>,
+    /// Public link while the session is shared
+    pub share_url: Option<String>,
+    /// Working directory of the session
+    pub directory: Option<String>,
+}
+
+/// Backend-agnostic model download progress
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullProgress {
+    pub status: String,
+    pub digest: Option<String>,
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+    pub percentage: Option<f64>,
+}
+
+impl PullProgress {
+    pub fn new(
+        status: String,
+        digest: Option<String>,
+        completed: Option<u64>,
+        total: Option<u64>,
+    ) -> Self {
+        let percentage = match (completed, total) {
+            (Some(completed), Some(total)) if total > 0 => {
+                Some((completed as f64 / total as f64 * 100.0).min(100.0))
+            }
+            _ => None,
+        };
+
+        Self {
+            status,
+            digest,
+            completed,
+            total,
+            percentage,
+        }
+    }
+
+    /// Progress update carrying only a status, e.g. `queued` or `cancelled`
+    pub fn status(status: &str) -> Self {
+        Self::new(status.to_string(), None, None, None)
+    }
+}
+
+/// Callback invoked with each model download progress update
+pub type ProgressCallback<'a> = dyn FnMut(&PullProgress) + Send + 'a;
+
+/// A streamed delta of the answer or of the model's reasoning, the latest
+/// state of a tool call the backend runs itself, or an agent asking for
+/// approval before it continues
+#[derive(Debug, Clone, Copy)]
+pub enum StreamChunk<'a> {
+    Content(&'a str),
+    Reasoning(&'a str),
+    ToolCall(&'a ToolCall),
+    PermissionRequest(&'a PermissionRequest),
+}
+
+/// An agent's request for approval before it runs a command or edits files
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRequest {
+    pub id: String,
+    pub session_id: String,
+    /// Kind of action, e.g. `bash` or `edit`
+    pub permission_type: String,
+    /// Commands or paths the approval covers
+    pub patterns: Vec<String>,
+    pub title: String,
+    /// Tool call waiting on the approval
+    pub call_id: Option<String>,
+    pub metadata: serde_json::Value,
+}
+
+/// Answer to a [`PermissionRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionResponse {
+    /// Allow this request only
+    Allow,
+    Deny,
+    /// Allow this and matching requests for the rest of the session
+    Always,
+}
+
+/// Callback invoked with each streamed delta
+pub type ChunkCallback<'a> = dyn FnMut(StreamChunk<'_>) + Send + 'a;
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ThinkState {
+    /// Waiting to see whether the content opens with a `<think>` block
+    #[default]
+    Start,
+    Thinking,
+    /// Past the reasoning; leading whitespace is still skipped
+    AfterThinking,
+    Content,
+}
+
+/// Splits a leading inline `<think>...</think>` block, as emitted by models
+/// such as deepseek-r1, out of streamed content.
+///
+/// Only a block at the very start of the content counts as reasoning, so an
+/// answer that merely mentions the tag is passed through untouched. Text that
+/// may be the start of a tag is held back until the next delta.
+#[derive(Debug, Default)]
+pub struct ThinkTagSplitter {
+    state: ThinkState,
+    pending: String,
+    /// Whether any reasoning was passed on, to skip its leading whitespace
+    reasoning_started: bool,
+}
+
+impl ThinkTagSplitter {
+    /// Feed a content delta, passing on the content and reasoning parts
+    pub fn push(&mut self, text: &str, on_chunk: &mut dyn FnMut(StreamChunk<'_>)) {
+        self.pending.push_str(text);
+        loop {
+            match self.state {
+                ThinkState::Start => {
+                    let trimmed = self.pending.trim_start();
+                    if let Some(rest) = trimmed.strip_prefix(THINK_OPEN) {
+                        self.pending = rest.to_string();
+                        self.state = ThinkState::Thinking;
+                    } else if THINK_OPEN.starts_with(trimmed) {
+                        return;
+                    } else {
+                        self.state = ThinkState::Content;
+                    }
+                }
+                ThinkState::Thinking => {
+                    if let Some(end) = self.pending.find(THINK_CLOSE) {
+                        let reasoning: String = self.pending.drain(..end).collect();
+                        self.pending.drain(..THINK_CLOSE.len());
+                        self.emit_reasoning(&reasoning, on_chunk);
+                        self.state = ThinkState::AfterThinking;
+                    } else {
+                        // Keep a possible partial closing tag for the next delta
+                        let keep = (1..THINK_CLOSE.len())
+                            .rev()
+                            .find(|len| self.pending.ends_with(&THINK_CLOSE[..*len]))
+                            .unwrap_or(0);
+                        let reasoning: String =
+                            self.pending.drain(..self.pending.len() - keep).collect();
+                        self.emit_reasoning(&reasoning, on_chunk);
+                        return;
+                    }
+                }
+                ThinkState::AfterThinking => {
+                    let trimmed = self.pending.trim_start();
+                    if trimmed.is_empty() {
+                        self.pending.clear();
+                        return;
+                    }
+                    self.pending = trimmed.to_string();
+                    self.state = ThinkState::Content;
+                }
+                ThinkState::Content => {
+                    if !self.pending.is_empty() {
+                        on_chunk(StreamChunk::Content(&self.pending));
+                        self.pending.clear();
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Flush text held back at the end of the stream
+    pub fn finish(&mut self, on_chunk: &mut dyn FnMut(StreamChunk<'_>)) {
+        let pending = std::mem::take(&mut self.pending);
+        match self.state {
+            ThinkState::Thinking => self.emit_reasoning(&pending, on_chunk),
+            _ if !pending.is_empty() => on_chunk(StreamChunk::Content(&pending)),
+            _ => {}
+        }
+    }
+
+    fn emit_reasoning(&mut self, text: &str, on_chunk: &mut dyn FnMut(StreamChunk<'_>)) {
+        let text = if self.reasoning_started {
+            text
+        } else {
+            text.trim_start()
+        };
+        if !text.is_empty() {
+            self.reasoning_started = true;
+            on_chunk(StreamChunk::Reasoning(text));
+        }
+    }
+}
+
+/// Split a leading `<think>` block out of a complete response, returning the
+/// content and the reasoning
+pub fn split_think_tags(text: &str) -> (String, Option<String>) {
+    let mut content = String::new();
+    let mut reasoning = String::new();
+    let mut splitter = ThinkTagSplitter::default();
+    let mut collect = |chunk: StreamChunk<'_>| match chunk {
+        StreamChunk::Content(text) => content.push_str(text),
+        StreamChunk::Reasoning(text) => reasoning.push_str(text),
+        StreamChunk::ToolCall(_) | StreamChunk::PermissionRequest(_) => {}
+    };
+    splitter.push(text, &mut collect);
+    splitter.finish(&mut collect);
+
+    let reasoning = reasoning.trim_end();
+    let reasoning = (!reasoning.is_empty()).then(|| reasoning.to_string());
+    (content, reasoning)
+}
+
+/// Common interface implemented by every chat backend client.
+///
+/// Commands only talk to backends through this trait, so adding a backend
+/// means implementing it and registering the client in [`create_backend`].
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Check if the backend server is reachable
+    async fn health_check(&self) -> Result<bool, String>;
+
+    /// List available models in the shared `OllamaModel` shape
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String>;
+
+    /// Send a chat request and wait for the full response
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String>;
+
+    /// Send a chat request, invoking `on_chunk` for each content delta.
+    ///
+    /// Backends without native streaming deliver the full response as one chunk.
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let response = self.chat(request).await?;
+        if let Some(reasoning) = response.reasoning.as_deref() {
+            on_chunk(StreamChunk::Reasoning(reasoning));
+        }
+        if !response.content.is_empty() {
+            on_chunk(StreamChunk::Content(&response.content));
+        }
+        Ok(response)
+    }
+
+    /// Generate a short conversation title using a side request to the same model
+    async fn generate_title(&self, model: &str, first_message: &str) -> Result<String, String> {
+        let request = BackendChatRequest {
+            model: model.to_string(),
+            messages: vec![Message::new(MessageRole::User, title_prompt(first_message))],
+            system_prompt: None,
+            parameters: ModelParameters {
+                temperature: 0.3,
+                max_tokens: Some(20),
+                ..ModelParameters::default()
+            },
+            session_id: None,
+            tools: Vec::new(),
+            think: None,
+            keep_alive: None,
+            response_format: None,
+        };
+
+        let response = self.chat(&request).await?;
+        let title = response.content.trim().trim_matches('"').to_string();
+        if title.is_empty() {
+            Ok(simple_title(first_message))
+        } else {
+            Ok(title)
+        }
+    }
+
+    /// Summarise conversation turns using a side request to `model`, folding
+    /// them into `previous_summary` when there is one
+    async fn summarize(
+        &self,
+        model: &str,
+        previous_summary: Option<&str>,
+        messages: &[Message],
+    ) -> Result<String, String> {
+        let request = BackendChatRequest {
+            model: model.to_string(),
+            messages: vec![Message::new(
+                MessageRole::User,
+                summary_prompt(previous_summary, messages),
+            )],
+            system_prompt: None,
+            parameters: ModelParameters {
+                temperature: 0.2,
+                max_tokens: Some(SUMMARY_MAX_TOKENS),
+                ..ModelParameters::default()
+            },
+            session_id: None,
+            tools: Vec::new(),
+            think: None,
+            keep_alive: None,
+            response_format: None,
+        };
+
+        let response = self.chat(&request).await?;
+        let summary = response.content.trim().to_string();
+        if summary.is_empty() {
+            Err("The model returned an empty summary".to_string())
+        } else {
+            Ok(summary)
+        }
+    }
+
+    /// Maximum context length of a model in tokens, if the backend reports one
+    async fn context_length(&self, _model: &str) -> Result<Option<u32>, String> {
+        Ok(None)
+    }
+
+    async fn show_model(&self, _model_name: &str) -> Result<serde_json::Value, String> {
+        Err(unsupported(self.kind(), "Showing model details"))
+    }
+
+    async fn pull_model(
+        &self,
+        _model_name: &str,
+        _on_progress: &mut ProgressCallback<'_>,
+    ) -> Result<String, String> {
+        Err(unsupported(self.kind(), "Pulling models"))
+    }
+
+    async fn delete_model(&self, _model_name: &str) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Deleting models"))
+    }
+
+    /// Create a model from a base model, reporting progress like a pull
+    async fn create_model(
+        &self,
+        _request: &ModelCreateRequest,
+        _on_progress: &mut ProgressCallback<'_>,
+    ) -> Result<String, String> {
+        Err(unsupported(self.kind(), "Creating models"))
+    }
+
+    /// Copy a model under a new name
+    async fn copy_model(&self, _source: &str, _destination: &str) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Copying models"))
+    }
+
+    /// Models currently loaded into memory
+    async fn list_running_models(&self) -> Result<Vec<OllamaRunningModel>, String> {
+        Err(unsupported(self.kind(), "Listing running models"))
+    }
+
+    /// Load a model ahead of the first request, keeping it loaded for
+    /// `keep_alive` or the server default
+    async fn preload_model(&self, _model: &str, _keep_alive: Option<&str>) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Preloading models"))
+    }
+
+    /// Unload a model, freeing the memory it occupies
+    async fn unload_model(&self, _model: &str) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Unloading models"))
+    }
+
+    /// Start an empty session, so a chat has a session id to abort before it
+    /// finishes
+    async fn create_session(&self) -> Result<String, String> {
+        Err(unsupported(self.kind(), "Creating sessions"))
+    }
+
+    /// Sessions held by the backend, newest first
+    async fn list_sessions(&self) -> Result<Vec<BackendSession>, String> {
+        Err(unsupported(self.kind(), "Listing sessions"))
+    }
+
+    /// Full history of a backend session
+    async fn session_messages(&self, _session_id: &str) -> Result<Vec<Message>, String> {
+        Err(unsupported(self.kind(), "Loading session history"))
+    }
+
+    /// Stop a running session; returns whether anything was running
+    async fn abort_session(&self, _session_id: &str) -> Result<bool, String> {
+        Err(unsupported(self.kind(), "Aborting sessions"))
+    }
+
+    async fn delete_session(&self, _session_id: &str) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Deleting sessions"))
+    }
+
+    /// Share a session publicly, or stop sharing it
+    async fn share_session(
+        &self,
+        _session_id: &str,
+        _shared: bool,
+    ) -> Result<BackendSession, String> {
+        Err(unsupported(self.kind(), "Sharing sessions"))
+    }
+
+    /// Compact a session's history into a summary using `model`
+    async fn summarize_session(&self, _session_id: &str, _model: &str) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Summarizing sessions"))
+    }
+
+    /// Answer a permission request streamed as [`StreamChunk::PermissionRequest`]
+    async fn respond_permission(
+        &self,
+        _session_id: &str,
+        _permission_id: &str,
+        _response: PermissionResponse,
+    ) -> Result<(), String> {
+        Err(unsupported(self.kind(), "Answering permission requests"))
+    }
+}
+
+/// Build a client for a configured connection
+pub fn create_backend(connection: &Connection) -> Result<Arc<dyn ChatBackend>, String> {
+    let kind = BackendKind::parse(Some(&connection.backend))
+        .ok_or_else(|| format!("Unknown backend type: {}", connection.backend))?;
+    let base_url = connection.base_url();
+
+    let backend: Arc<dyn ChatBackend> = match kind {
+        BackendKind::Ollama => Arc::new(OllamaClient::new(&base_url)),
+        BackendKind::Opencode => {
+            let mut client = OpencodeClient::new(&base_url);
+            client.update_connection(
+                &base_url,
+                connection.username.clone(),
+                connection.password.clone(),
+            );
+            Arc::new(client)
+        }
+        BackendKind::LmStudio => {
+            let mut client = LmStudioClient::new(&base_url);
+            client.update_connection(&base_url, connection.api_token.clone());
+            Arc::new(client)
+        }
+        BackendKind::OpenAiCompat => {
+            // Plain host/port connections point at a server root, not its API path
+            let has_api_base_url = connection
+                .api_base_url
+                .as_deref()
+                .is_some_and(|url| !url.trim().is_empty());
+            let api_base_url = if has_api_base_url {
+                base_url
+            } else {
+                format!("{base_url}/v1")
+            };
+            Arc::new(OpenAiCompatClient::new(
+                &api_base_url,
+                connection.api_token.clone(),
+            ))
+        }
+        BackendKind::Anthropic => Arc::new(AnthropicClient::new(
+            &base_url,
+            connection.api_token.clone(),
+        )),
+        BackendKind::Gemini => Arc::new(GeminiClient::new(&base_url, connection.api_token.clone())),
+    };
+
+    Ok(backend)
+}
+
+fn unsupported(kind: BackendKind, action: &str) -> String {
+    format!("{action} is not supported by the {} backend", kind.label())
+}
+
+/// Upper bound on the length of a generated conversation summary
+pub const SUMMARY_MAX_TOKENS: u32 = 512;
+
+/// Longest message text included in a summary request
+const SUMMARY_MESSAGE_CHARS: usize = 2000;
+
+pub fn summary_prompt(previous_summary: Option<&str>, messages: &[Message]) -> String {
+    let transcript = messages
+        .iter()
+        .filter(|message| !message.content.trim().is_empty())
+        .map(|message| {
+            let role = match message.role {
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+                MessageRole::System => "System",
+                MessageRole::Tool => "Tool",
+            };
+            let content: String = message
+                .content
+                .trim()
+                .chars()
+                .take(SUMMARY_MESSAGE_CHARS)
+                .collect();
+            format!("{role}: {content}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let instructions = "Keep names, decisions, facts, code identifiers and open questions the conversation may refer back to. Reply with the summary only.";
+    match previous_summary.map(str::trim).filter(|summary| !summary.is_empty()) {
+        Some(summary) => format!(
+            "Update this summary of a conversation with the messages that follow it, in a few short paragraphs. {instructions}\n\nSummary so far:\n{summary}\n\nNew messages:\n{transcript}"
+        ),
+        None => format!(
+            "Summarise the following conversation in a few short paragraphs. {instructions}\n\n{transcript}"
+        ),
+    }
+}
+
+pub fn title_prompt(first_message: &str) -> String {
+    format!(
+        "Generate a very short title (3-6 words, no quotes) for a conversation that starts with: {}",
+        first_message.chars().take(200).collect::<String>()
+    )
+}
+
+pub fn simple_title(first_message: &str) -> String {
+    let title = first_message
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .chars()
+        .take(60)
+        .collect::<String>();
+
+    if title.is_empty() {
+        "New Conversation".to_string()
+    } else {
+        title
+    }
+}
+
+pub fn latest_user_text(messages: &[Message]) -> Option<String> {
+    messages
+        .iter()
+        .rev()
+        .find(|message| message.role == MessageRole::User)
+        .map(|message| message.content.trim().to_string())
+        .filter(|message| !message.is_empty())
+}
+
+pub fn latest_user_attachments(messages: &[Message]) -> Vec<Attachment> {
+    messages
+        .iter()
+        .rev()
+        .find(|message| message.role == MessageRole::User)
+        .map(|message| message.attachments.clone())
+        .unwrap_or_default()
+}
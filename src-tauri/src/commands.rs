@@ -1,159 +1,152 @@
+use crate::backend::{
+    create_backend, BackendCapabilities, BackendChatRequest, BackendChatResponse, BackendKind,
+    BackendSession, ChatBackend, PermissionResponse, PullProgress, StreamChunk,
+};
+use crate::connections::{self, ConnectionRegistry, ConnectionStatus};
+use crate::context::ContextPlanner;
+use crate::downloads::{DownloadManager, PullInfo, PullProgressEvent, PullState};
 use crate::models::*;
-use crate::lm_studio::LmStudioClient;
-use crate::ollama::{OllamaClient, messages_to_ollama};
-use crate::opencode::{OpencodeClient, OpencodeModelRef};
-use std::sync::Mutex;
+use crate::structured::{self, ResponseFormat, StructuredOutput};
+use crate::tools::{ToolContext, ToolDefinition, ToolRegistry};
+use crate::usage::{UsageBudget, UsageRecord, UsageReport, UsageTracker};
+use chrono::{DateTime, Utc};
+use futures::future::{AbortHandle, Abortable, Aborted};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tauri::State;
-use tauri::{AppHandle, Emitter};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum BackendKind {
-    Ollama,
-    Opencode,
-    LmStudio,
-}
-
-impl BackendKind {
-    fn parse(value: Option<&str>) -> Option<Self> {
-        match value
-            .map(str::trim)
-            .filter(|v| !v.is_empty())
-            .map(|v| v.to_lowercase())
-            .as_deref()
-        {
-            Some("opencode") => Some(Self::Opencode),
-            Some("ollama") => Some(Self::Ollama),
-            Some("lmstudio") | Some("lm-studio") | Some("lm_studio") => Some(Self::LmStudio),
-            _ => None,
-        }
-    }
-
-    fn label(self) -> &'static str {
-        match self {
-            Self::Ollama => "Ollama",
-            Self::Opencode => "OpenCode",
-            Self::LmStudio => "LM Studio",
-        }
-    }
-
-}
+use tauri::{AppHandle, Emitter, Manager};
 
 pub struct AppState {
-    pub ollama_client: Mutex<OllamaClient>,
-    pub opencode_client: Mutex<OpencodeClient>,
-    pub lm_studio_client: Mutex<LmStudioClient>,
-    pub connected: Mutex<bool>,
-    active_backend: Mutex<BackendKind>,
+    connections: Mutex<ConnectionRegistry>,
+    /// Cloud providers saved with `save_cloud_provider`, keyed by connection id
+    cloud_providers: Mutex<Vec<CloudProvider>>,
+    /// Abort handles for in-flight `send_message` calls, keyed by request id
+    active_requests: Mutex<HashMap<String, AbortHandle>>,
+    downloads: DownloadManager,
+    tools: ToolRegistry,
+    /// Tool settings pushed from the frontend with `update_tool_config`
+    tool_config: Mutex<ToolConfig>,
+    usage: UsageTracker,
+    context: ContextPlanner,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            ollama_client: Mutex::new(OllamaClient::new("http://localhost:11434")),
-            opencode_client: Mutex::new(OpencodeClient::new("http://localhost:4096")),
-            lm_studio_client: Mutex::new(LmStudioClient::new("http://localhost:1234")),
-            connected: Mutex::new(false),
-            active_backend: Mutex::new(BackendKind::Ollama),
+            connections: Mutex::new(ConnectionRegistry::with_local_defaults()),
+            cloud_providers: Mutex::new(Vec::new()),
+            active_requests: Mutex::new(HashMap::new()),
+            downloads: DownloadManager::new(),
+            tools: ToolRegistry::with_builtin_tools(),
+            tool_config: Mutex::new(ToolConfig::default()),
+            usage: UsageTracker::new(),
+            context: ContextPlanner::new(),
         }
     }
 }
 
-fn get_ollama_client(state: &State<'_, AppState>) -> Result<OllamaClient, String> {
-    let client = state.ollama_client.lock().map_err(|e| e.to_string())?.clone();
-    Ok(client)
-}
-
-fn get_opencode_client(state: &State<'_, AppState>) -> Result<OpencodeClient, String> {
-    let client = state.opencode_client.lock().map_err(|e| e.to_string())?.clone();
-    Ok(client)
+/// Replace the startup connections with the registry saved in the app data
+/// directory
+pub fn load_connections(app: &AppHandle) -> Result<(), String> {
+    let registry = ConnectionRegistry::load(&app_data_dir(app)?)?;
+    let state = app.state::<AppState>();
+    *state.connections.lock().map_err(|e| e.to_string())? = registry;
+    Ok(())
 }
 
-fn get_lm_studio_client(state: &State<'_, AppState>) -> Result<LmStudioClient, String> {
-    let client = state.lm_studio_client.lock().map_err(|e| e.to_string())?.clone();
-    Ok(client)
+/// Restore saved cloud providers: their connections, prices and context lengths
+pub fn load_cloud_providers(app: &AppHandle) -> Result<(), String> {
+    let data_dir = app_data_dir(app)?;
+    let providers = connections::read_cloud_providers(&data_dir)?;
+    let state = app.state::<AppState>();
+    for provider in &providers {
+        state
+            .usage
+            .set_prices(&data_dir, &provider.id, &provider.models)?;
+        state
+            .context
+            .set_cloud_models(&provider.id, &provider.models)?;
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        // The saved registry already holds the connection unless it was lost
+        if connections.get(&provider.id).is_none() {
+            connections.upsert(provider.to_connection()?)?;
+        }
+    }
+    *state.cloud_providers.lock().map_err(|e| e.to_string())? = providers;
+    Ok(())
 }
 
-fn get_active_backend(state: &State<'_, AppState>) -> Result<BackendKind, String> {
-    let backend = *state.active_backend.lock().map_err(|e| e.to_string())?;
-    Ok(backend)
+fn save_connections(app: &AppHandle, state: &State<'_, AppState>) -> Result<(), String> {
+    let data_dir = app_data_dir(app)?;
+    let connections = state.connections.lock().map_err(|e| e.to_string())?;
+    connections.save(&data_dir)
 }
 
-fn parse_model_selector(model: &str) -> Option<OpencodeModelRef> {
-    let (provider_id, model_id) = model.split_once('/')?;
-    if provider_id.trim().is_empty() || model_id.trim().is_empty() {
-        return None;
-    }
-
-    Some(OpencodeModelRef {
-        provider_id: provider_id.to_string(),
-        model_id: model_id.to_string(),
-    })
+/// Resolve a connection by id, backend kind or the default connection
+fn resolve_connection(
+    state: &State<'_, AppState>,
+    connection_id: Option<&str>,
+    backend: Option<&str>,
+) -> Result<(String, Arc<dyn ChatBackend>), String> {
+    let connections = state.connections.lock().map_err(|e| e.to_string())?;
+    connections.resolve(connection_id, BackendKind::parse(backend))
 }
 
-fn strip_lm_studio_prefix(model: &str) -> &str {
-    model.strip_prefix("lmstudio:").unwrap_or(model)
+fn get_backend(
+    state: &State<'_, AppState>,
+    connection_id: Option<&str>,
+) -> Result<Arc<dyn ChatBackend>, String> {
+    resolve_connection(state, connection_id, None).map(|(_, backend)| backend)
 }
 
-fn latest_user_text(messages: &[Message]) -> Option<String> {
-    messages
-        .iter()
-        .rev()
-        .find(|message| message.role == MessageRole::User)
-        .map(|message| message.content.trim().to_string())
-        .filter(|message| !message.is_empty())
+/// Snapshot the tool settings and paths for a tool run
+fn tool_context(app: &AppHandle, state: &State<'_, AppState>) -> Result<ToolContext, String> {
+    let config = state.tool_config.lock().map_err(|e| e.to_string())?.clone();
+    Ok(ToolContext {
+        config,
+        cache_dir: app.path().app_cache_dir().ok(),
+    })
 }
 
-fn latest_user_attachments(messages: &[Message]) -> Vec<Attachment> {
-    messages
-        .iter()
-        .rev()
-        .find(|message| message.role == MessageRole::User)
-        .map(|message| message.attachments.clone())
-        .unwrap_or_default()
+fn record_health(
+    state: &State<'_, AppState>,
+    connection_id: &str,
+    healthy: bool,
+) -> Result<(), String> {
+    let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+    connections.set_health(connection_id, healthy);
+    Ok(())
 }
 
-fn build_lm_studio_prompt(messages: &[Message]) -> String {
-    let mut lines = Vec::new();
-
-    for message in messages {
-        let content = message.content.trim();
-        if content.is_empty() {
-            continue;
-        }
-
-        let role = match message.role {
-            MessageRole::User => "User",
-            MessageRole::Assistant => "Assistant",
-            MessageRole::System => "System",
-            MessageRole::Tool => "Tool",
-        };
-
-        lines.push(format!("{role}: {content}"));
-    }
-
-    lines.join("\n")
+/// Directory holding app-wide data such as the usage ledger
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| e.to_string())
 }
 
-fn simple_title(first_message: &str) -> String {
-    let title = first_message
-        .lines()
-        .next()
-        .unwrap_or("")
-        .trim()
-        .chars()
-        .take(60)
-        .collect::<String>();
-
-    if title.is_empty() {
-        "New Conversation".to_string()
-    } else {
-        title
-    }
+fn chat_response_json(connection_id: &str, response: &BackendChatResponse) -> serde_json::Value {
+    serde_json::json!({
+        "content": response.content,
+        "reasoning": response.reasoning,
+        "prompt_eval_count": response.prompt_eval_count,
+        "eval_count": response.eval_count,
+        "cost": response.cost,
+        "total_duration": response.total_duration,
+        "session_id": response.session_id,
+        "connection_id": connection_id,
+        "tool_calls": response.tool_calls,
+        "done": true,
+    })
 }
 
-/// Test connection to backend server
+/// Test connection to backend server and register it on success.
+///
+/// Without a `connection_id` the existing connection of the same backend kind
+/// is replaced, matching the single-connection-per-backend behaviour. Omitted
+/// credentials keep the ones already saved for the connection.
 #[tauri::command]
 pub async fn test_connection(
+    app: AppHandle,
     state: State<'_, AppState>,
     host: String,
     port: u16,
@@ -163,154 +156,258 @@ pub async fn test_connection(
     username: Option<String>,
     password: Option<String>,
     api_token: Option<String>,
+    connection_id: Option<String>,
+    name: Option<String>,
 ) -> Result<bool, String> {
-    let backend_kind = BackendKind::parse(backend.as_deref().or(backend_type.as_deref()))
-        .unwrap_or(get_active_backend(&state)?);
-    let scheme = if use_https { "https" } else { "http" };
-    let base_url = format!("{}://{}:{}", scheme, host, port);
-
-    let result = match backend_kind {
-        BackendKind::Ollama => {
-            let client = OllamaClient::new(&base_url);
-            let result = client.health_check().await?;
-            if result {
-                let mut ollama = state.ollama_client.lock().map_err(|e| e.to_string())?;
-                ollama.update_base_url(&base_url);
-            }
-            result
-        }
-        BackendKind::Opencode => {
-            let mut client = OpencodeClient::new(&base_url);
-            client.update_connection(&base_url, username, password);
-            let result = client.health_check().await?;
-            if result {
-                let mut opencode = state.opencode_client.lock().map_err(|e| e.to_string())?;
-                *opencode = client;
-            }
-            result
-        }
-        BackendKind::LmStudio => {
-            let mut client = LmStudioClient::new(&base_url);
-            client.update_connection(&base_url, api_token);
-            let result = client.health_check().await?;
-            if result {
-                let mut lm_studio = state.lm_studio_client.lock().map_err(|e| e.to_string())?;
-                *lm_studio = client;
-            }
-            result
+    let connection = {
+        let connections = state.connections.lock().map_err(|e| e.to_string())?;
+        let backend_kind = BackendKind::parse(backend.as_deref().or(backend_type.as_deref()))
+            .or_else(|| {
+                connections
+                    .default_id()
+                    .and_then(|id| connections.get(id))
+                    .map(|entry| entry.kind)
+            })
+            .unwrap_or(BackendKind::Ollama);
+
+        let existing = match connection_id.as_deref() {
+            Some(id) => connections.get(id),
+            None => connections.find_by_kind(backend_kind),
+        };
+
+        Connection {
+            id: connection_id
+                .or_else(|| existing.map(|entry| entry.connection.id.clone()))
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: name
+                .or_else(|| existing.map(|entry| entry.connection.name.clone()))
+                .unwrap_or_else(|| backend_kind.label().to_string()),
+            backend: backend_kind.key().to_string(),
+            host,
+            port,
+            use_https,
+            username: username
+                .or_else(|| existing.and_then(|entry| entry.connection.username.clone())),
+            password: password
+                .or_else(|| existing.and_then(|entry| entry.connection.password.clone())),
+            api_token: api_token
+                .or_else(|| existing.and_then(|entry| entry.connection.api_token.clone())),
+            api_base_url: existing.and_then(|entry| entry.connection.api_base_url.clone()),
+            is_default: existing.is_some_and(|entry| entry.connection.is_default),
+            created_at: existing
+                .map(|entry| entry.connection.created_at)
+                .unwrap_or_else(chrono::Utc::now),
+            last_connected_at: existing.and_then(|entry| entry.connection.last_connected_at),
         }
     };
 
-    {
-        let mut connected = state.connected.lock().map_err(|e| e.to_string())?;
-        *connected = result;
-    }
+    let client = create_backend(&connection)?;
+    let result = client.health_check().await?;
 
     if result {
-        let mut active_backend = state.active_backend.lock().map_err(|e| e.to_string())?;
-        *active_backend = backend_kind;
+        let id = connection.id.clone();
+        {
+            let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+            connections.upsert(connection)?;
+            connections.set_health(&id, true);
+        }
+        save_connections(&app, &state)?;
     }
 
     Ok(result)
 }
 
-/// Get current connection status
+/// Register or update a named connection and check its health
 #[tauri::command]
-pub async fn get_connection_status(state: State<'_, AppState>) -> Result<bool, String> {
-    let backend = get_active_backend(&state)?;
-    let result = match backend {
-        BackendKind::Ollama => {
-            let ollama = get_ollama_client(&state)?;
-            ollama.health_check().await
-        }
-        BackendKind::Opencode => {
-            let opencode = get_opencode_client(&state)?;
-            opencode.health_check().await
-        }
-        BackendKind::LmStudio => {
-            let lm_studio = get_lm_studio_client(&state)?;
-            lm_studio.health_check().await
+pub async fn save_connection(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection: Connection,
+) -> Result<ConnectionStatus, String> {
+    let status = register_connection(&state, connection).await?;
+    save_connections(&app, &state)?;
+    Ok(status)
+}
+
+/// Register a cloud provider as a connection using its base URL and API key,
+/// and save it so it is restored on the next start
+#[tauri::command]
+pub async fn save_cloud_provider(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    provider: CloudProvider,
+) -> Result<ConnectionStatus, String> {
+    let connection = provider.to_connection()?;
+    let data_dir = app_data_dir(&app)?;
+    state
+        .usage
+        .set_prices(&data_dir, &provider.id, &provider.models)?;
+    let status = register_connection(&state, connection).await?;
+    state
+        .context
+        .set_cloud_models(&provider.id, &provider.models)?;
+
+    {
+        let mut providers = state.cloud_providers.lock().map_err(|e| e.to_string())?;
+        match providers.iter_mut().find(|saved| saved.id == provider.id) {
+            Some(saved) => *saved = provider,
+            None => providers.push(provider),
         }
+        connections::write_cloud_providers(&data_dir, &providers)?;
+    }
+    save_connections(&app, &state)?;
+    Ok(status)
+}
+
+/// List the saved cloud providers
+#[tauri::command]
+pub fn list_cloud_providers(state: State<'_, AppState>) -> Result<Vec<CloudProvider>, String> {
+    Ok(state
+        .cloud_providers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone())
+}
+
+/// Add or replace a connection and record whether it is reachable
+async fn register_connection(
+    state: &State<'_, AppState>,
+    connection: Connection,
+) -> Result<ConnectionStatus, String> {
+    let id = connection.id.clone();
+    state.context.forget_connection(&id)?;
+    let backend = {
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        connections.upsert(connection)?
     };
 
-    match result {
-        Ok(healthy) => {
-            let mut connected = state.connected.lock().map_err(|e| e.to_string())?;
-            *connected = healthy;
-            Ok(healthy)
-        }
-        Err(_) => {
-            let mut connected = state.connected.lock().map_err(|e| e.to_string())?;
-            *connected = false;
-            Ok(false)
+    let healthy = backend.health_check().await.unwrap_or(false);
+
+    let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+    connections.set_health(&id, healthy);
+    connections
+        .status(&id)
+        .ok_or_else(|| format!("Unknown connection: {id}"))
+}
+
+/// Remove a registered connection
+#[tauri::command]
+pub fn remove_connection(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+) -> Result<bool, String> {
+    let data_dir = app_data_dir(&app)?;
+    state.usage.remove_prices(&data_dir, &connection_id)?;
+    state.context.forget_connection(&connection_id)?;
+    {
+        let mut providers = state.cloud_providers.lock().map_err(|e| e.to_string())?;
+        if providers.iter().any(|provider| provider.id == connection_id) {
+            providers.retain(|provider| provider.id != connection_id);
+            connections::write_cloud_providers(&data_dir, &providers)?;
+            connections::delete_cloud_provider_secret(&connection_id);
         }
     }
+    let removed = {
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        connections.remove(&connection_id)
+    };
+    save_connections(&app, &state)?;
+    Ok(removed)
 }
 
-/// List available models from active backend
+/// List all registered connections with their last known health
 #[tauri::command]
-pub async fn list_models(state: State<'_, AppState>) -> Result<Vec<OllamaModel>, String> {
-    match get_active_backend(&state)? {
-        BackendKind::Ollama => {
-            let ollama = get_ollama_client(&state)?;
-            ollama.list_models().await
+pub fn list_connections(state: State<'_, AppState>) -> Result<Vec<ConnectionStatus>, String> {
+    let connections = state.connections.lock().map_err(|e| e.to_string())?;
+    Ok(connections.statuses())
+}
+
+/// Choose the connection used when a command does not name one
+#[tauri::command]
+pub fn set_default_connection(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    connection_id: String,
+) -> Result<(), String> {
+    {
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        if connections.get(&connection_id).is_none() {
+            return Err(format!("Unknown connection: {connection_id}"));
         }
-        BackendKind::Opencode => {
-            let opencode = get_opencode_client(&state)?;
-            let models = opencode.list_models().await?;
-            Ok(models
-                .into_iter()
-                .map(|m| OllamaModel {
-                    name: format!("{}/{}", m.provider_id, m.model_id),
-                    modified_at: None,
-                    size: None,
-                    digest: None,
-                    details: Some(OllamaModelDetails {
-                        format: Some("opencode".to_string()),
-                        family: Some(m.provider_id),
-                        families: None,
-                        parameter_size: None,
-                        quantization_level: Some(m.display_name),
-                    }),
-                })
-                .collect())
+        connections.set_default(&connection_id);
+    }
+    save_connections(&app, &state)
+}
+
+/// Get current connection status
+#[tauri::command]
+pub async fn get_connection_status(
+    state: State<'_, AppState>,
+    connection_id: Option<String>,
+) -> Result<bool, String> {
+    let (id, backend) = resolve_connection(&state, connection_id.as_deref(), None)?;
+    let healthy = backend.health_check().await.unwrap_or(false);
+
+    record_health(&state, &id, healthy)?;
+    Ok(healthy)
+}
+
+/// Get the optional features supported by a connection's backend
+#[tauri::command]
+pub fn get_backend_capabilities(
+    state: State<'_, AppState>,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<BackendCapabilities, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    Ok(client.capabilities())
+}
+
+/// List available models from one connection, or merged from every healthy connection
+#[tauri::command]
+pub async fn list_models(
+    state: State<'_, AppState>,
+    connection_id: Option<String>,
+) -> Result<Vec<OllamaModel>, String> {
+    let targets = match connection_id.as_deref() {
+        Some(id) => vec![resolve_connection(&state, Some(id), None)?],
+        None => {
+            let connections = state.connections.lock().map_err(|e| e.to_string())?;
+            connections.backends()
         }
-        BackendKind::LmStudio => {
-            let lm_studio = get_lm_studio_client(&state)?;
-            let models = lm_studio.list_models().await?;
-            Ok(models
-                .into_iter()
-                .map(|model| {
-                    let model_key = model.key.clone();
-                    let family = model.publisher.clone();
-                    let display_name = model.display_name_or_key();
-                    let parameter_size = model
-                        .params_string
-                        .clone()
-                        .or_else(|| model.max_context_length.map(|value| format!("{value} ctx")));
-                    let quantization_level = model
-                        .quantization
-                        .as_ref()
-                        .and_then(|quantization| quantization.name.clone());
-
-                    OllamaModel {
-                        name: format!("lmstudio:{}", model_key),
-                        modified_at: None,
-                        size: Some(model.size_bytes),
-                        digest: None,
-                        details: Some(OllamaModelDetails {
-                            format: Some("lmstudio".to_string()),
-                            family: Some(family),
-                            families: None,
-                            parameter_size,
-                            quantization_level: quantization_level
-                                .or_else(|| Some(display_name)),
-                        }),
-                    }
-                })
-                .collect())
+    };
+
+    let results = futures::future::join_all(targets.iter().map(|(id, backend)| async move {
+        (id.clone(), backend.list_models().await)
+    }))
+    .await;
+
+    let mut models = Vec::new();
+    let mut first_error = None;
+    let mut any_healthy = false;
+
+    for (id, result) in results {
+        match result {
+            Ok(listed) => {
+                any_healthy = true;
+                record_health(&state, &id, true)?;
+                models.extend(listed.into_iter().map(|mut model| {
+                    model.connection_id = Some(id.clone());
+                    model
+                }));
+            }
+            Err(error) => {
+                record_health(&state, &id, false)?;
+                first_error.get_or_insert(error);
+            }
         }
     }
+
+    match first_error {
+        Some(error) if !any_healthy => Err(error),
+        _ => Ok(models),
+    }
 }
 
 /// Show model details
@@ -318,33 +415,89 @@ pub async fn list_models(state: State<'_, AppState>) -> Result<Vec<OllamaModel>,
 pub async fn show_model(
     state: State<'_, AppState>,
     model_name: String,
+    connection_id: Option<String>,
 ) -> Result<serde_json::Value, String> {
-    let backend = get_active_backend(&state)?;
-    if backend != BackendKind::Ollama {
-        return Err(format!(
-            "Model details are only available for Ollama. Current backend: {}",
-            backend.label()
-        ));
-    }
-    let ollama = get_ollama_client(&state)?;
-    ollama.show_model(&model_name).await
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.show_model(&model_name).await
 }
 
-/// Pull (download) a model
+/// Pull (download) a model, emitting `model_pull_progress` events.
+///
+/// Pulls wait in the download manager's queue until a slot is free, so
+/// several can be requested at once.
 #[tauri::command]
 pub async fn pull_model(
+    app: AppHandle,
     state: State<'_, AppState>,
     model_name: String,
+    connection_id: Option<String>,
 ) -> Result<String, String> {
-    let backend = get_active_backend(&state)?;
-    if backend != BackendKind::Ollama {
-        return Err(format!(
-            "Pulling models is only supported for Ollama. Current backend: {}",
-            backend.label()
-        ));
+    let (connection_id, backend) = resolve_connection(&state, connection_id.as_deref(), None)?;
+    let downloads = &state.downloads;
+    let (pull_id, abort_registration) = downloads.enqueue(&model_name, &connection_id)?;
+
+    let emit_progress = |progress: PullProgress, done: bool, cancelled: bool, error: Option<&str>| {
+        let _ = app.emit("model_pull_progress", PullProgressEvent {
+            pull_id: &pull_id,
+            model_name: &model_name,
+            connection_id: &connection_id,
+            progress,
+            done,
+            cancelled,
+            error,
+        });
+    };
+
+    emit_progress(PullProgress::status("queued"), false, false, None);
+
+    let pull = async {
+        let _slot = downloads.acquire_slot().await?;
+        downloads.set_state(&pull_id, PullState::Downloading);
+
+        backend
+            .pull_model(&model_name, &mut |progress| {
+                downloads.update_progress(&pull_id, progress);
+                emit_progress(progress.clone(), false, false, None);
+            })
+            .await
+    };
+
+    let result = Abortable::new(pull, abort_registration).await;
+    downloads.finish(&pull_id);
+
+    match result {
+        Ok(Ok(message)) => {
+            let progress = PullProgress {
+                percentage: Some(100.0),
+                ..PullProgress::status("success")
+            };
+            emit_progress(progress, true, false, None);
+            Ok(message)
+        }
+        Ok(Err(error)) => {
+            emit_progress(PullProgress::status("error"), true, false, Some(&error));
+            Err(error)
+        }
+        Err(Aborted) => {
+            emit_progress(PullProgress::status("cancelled"), true, true, None);
+            Err(format!("Pull of {} was cancelled", model_name))
+        }
     }
-    let ollama = get_ollama_client(&state)?;
-    ollama.pull_model(&model_name).await
+}
+
+/// Cancel a queued or running pull by pull id or model name.
+///
+/// Ollama keeps the layers downloaded so far, so pulling the model again
+/// resumes the download.
+#[tauri::command]
+pub fn cancel_pull(state: State<'_, AppState>, pull_id: String) -> Result<bool, String> {
+    state.downloads.cancel(&pull_id)
+}
+
+/// List queued and running model pulls
+#[tauri::command]
+pub fn list_pulls(state: State<'_, AppState>) -> Result<Vec<PullInfo>, String> {
+    state.downloads.list()
 }
 
 /// Delete a model
@@ -352,16 +505,227 @@ pub async fn pull_model(
 pub async fn delete_model(
     state: State<'_, AppState>,
     model_name: String,
+    connection_id: Option<String>,
+) -> Result<(), String> {
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.delete_model(&model_name).await
+}
+
+/// Create a model from a base model with a baked-in system prompt, template,
+/// parameters and adapter, emitting `model_create_progress` events
+#[tauri::command]
+pub async fn create_model(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    request: ModelCreateRequest,
+    connection_id: Option<String>,
+) -> Result<String, String> {
+    let (connection_id, backend) = resolve_connection(&state, connection_id.as_deref(), None)?;
+    let name = request.name.trim();
+    if name.is_empty() || request.from.trim().is_empty() {
+        return Err("A model name and base model are required".to_string());
+    }
+
+    let emit_progress = |progress: PullProgress, done: bool, error: Option<&str>| {
+        let _ = app.emit("model_create_progress", serde_json::json!({
+            "modelName": name,
+            "connectionId": &connection_id,
+            "status": progress.status,
+            "digest": progress.digest,
+            "completed": progress.completed,
+            "total": progress.total,
+            "percentage": progress.percentage,
+            "done": done,
+            "error": error,
+        }));
+    };
+
+    let result = backend
+        .create_model(&request, &mut |progress| emit_progress(progress.clone(), false, None))
+        .await;
+    match &result {
+        Ok(_) => emit_progress(PullProgress::status("success"), true, None),
+        Err(error) => emit_progress(PullProgress::status("error"), true, Some(error)),
+    }
+    result
+}
+
+/// Copy a model under a new name
+#[tauri::command]
+pub async fn copy_model(
+    state: State<'_, AppState>,
+    source: String,
+    destination: String,
+    connection_id: Option<String>,
 ) -> Result<(), String> {
-    let backend = get_active_backend(&state)?;
-    if backend != BackendKind::Ollama {
-        return Err(format!(
-            "Deleting models is only supported for Ollama. Current backend: {}",
-            backend.label()
-        ));
+    let (source, destination) = model_names(&source, &destination)?;
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.copy_model(&source, &destination).await
+}
+
+/// Rename a model by copying it and deleting the original
+#[tauri::command]
+pub async fn rename_model(
+    state: State<'_, AppState>,
+    source: String,
+    destination: String,
+    connection_id: Option<String>,
+) -> Result<(), String> {
+    // `llama3` and `llama3:latest` name the same model, which must not be
+    // copied onto itself and then deleted
+    let (source, destination) = model_names(&source, &destination)?;
+    if destination == source {
+        return Ok(());
+    }
+
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.copy_model(&source, &destination).await?;
+    backend.delete_model(&source).await.map_err(|error| {
+        format!("Copied {source} to {destination} but could not remove the original: {error}")
+    })
+}
+
+/// Trimmed source and destination names of a copy, with their tags spelled out
+fn model_names(source: &str, destination: &str) -> Result<(String, String), String> {
+    let (source, destination) = (source.trim(), destination.trim());
+    if source.is_empty() || destination.is_empty() {
+        return Err("Model name cannot be empty".to_string());
+    }
+    Ok((with_default_tag(source), with_default_tag(destination)))
+}
+
+/// Model name with the implicit `:latest` tag spelled out; a colon before the
+/// last `/` belongs to a registry port, not a tag
+fn with_default_tag(name: &str) -> String {
+    let model = name.rsplit('/').next().unwrap_or(name);
+    if model.contains(':') {
+        name.to_string()
+    } else {
+        format!("{name}:latest")
+    }
+}
+
+/// Export a model's Modelfile for editing
+#[tauri::command]
+pub async fn export_modelfile(
+    state: State<'_, AppState>,
+    model_name: String,
+    connection_id: Option<String>,
+) -> Result<String, String> {
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    let info = backend.show_model(&model_name).await?;
+    info.get("modelfile")
+        .and_then(|modelfile| modelfile.as_str())
+        .map(|modelfile| modelfile.to_string())
+        .ok_or_else(|| {
+            let backend = backend.kind().label();
+            format!("{backend} did not return a Modelfile for {model_name}")
+        })
+}
+
+/// How often the running-model watcher polls the connections
+const RUNNING_MODELS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Loaded models across every connection that reports them; connections that
+/// fail or cannot list running models are skipped
+async fn running_models(targets: &[(String, Arc<dyn ChatBackend>)]) -> Vec<OllamaRunningModel> {
+    let results = futures::future::join_all(targets.iter().map(|(id, backend)| async move {
+        (id.clone(), backend.list_running_models().await)
+    }))
+    .await;
+
+    results
+        .into_iter()
+        .filter_map(|(id, result)| result.ok().map(|models| (id, models)))
+        .flat_map(|(id, models)| {
+            models.into_iter().map(move |mut model| {
+                model.connection_id = Some(id.clone());
+                model
+            })
+        })
+        .collect()
+}
+
+/// List models loaded into memory on one connection, or on every connection
+#[tauri::command]
+pub async fn list_running_models(
+    state: State<'_, AppState>,
+    connection_id: Option<String>,
+) -> Result<Vec<OllamaRunningModel>, String> {
+    match connection_id.as_deref() {
+        Some(id) => {
+            let (id, backend) = resolve_connection(&state, Some(id), None)?;
+            let models = backend.list_running_models().await?;
+            Ok(models
+                .into_iter()
+                .map(|mut model| {
+                    model.connection_id = Some(id.clone());
+                    model
+                })
+                .collect())
+        }
+        None => {
+            let targets = state.connections.lock().map_err(|e| e.to_string())?.backends();
+            Ok(running_models(&targets).await)
+        }
     }
-    let ollama = get_ollama_client(&state)?;
-    ollama.delete_model(&model_name).await
+}
+
+/// Load a model into memory before the first message is sent
+#[tauri::command]
+pub async fn preload_model(
+    state: State<'_, AppState>,
+    model_name: String,
+    keep_alive: Option<String>,
+    connection_id: Option<String>,
+) -> Result<(), String> {
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    let keep_alive = keep_alive.filter(|keep_alive| !keep_alive.trim().is_empty());
+    backend.preload_model(&model_name, keep_alive.as_deref()).await
+}
+
+/// Unload a model, freeing the memory it occupies
+#[tauri::command]
+pub async fn unload_model(
+    state: State<'_, AppState>,
+    model_name: String,
+    connection_id: Option<String>,
+) -> Result<(), String> {
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.unload_model(&model_name).await
+}
+
+/// Poll the connections for loaded models in the background, emitting
+/// `running_models_changed` with the full list whenever a model is loaded,
+/// unloaded or moves between GPU and CPU memory
+pub fn watch_running_models(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RUNNING_MODELS_POLL_INTERVAL);
+        let mut previous: Option<Vec<(String, String, Option<u64>)>> = None;
+        loop {
+            interval.tick().await;
+
+            let targets = match app.state::<AppState>().connections.lock() {
+                Ok(connections) => connections.backends(),
+                Err(_) => continue,
+            };
+            let models = running_models(&targets).await;
+
+            // Expiry times move with every request, so they don't count as a change
+            let mut key: Vec<_> = models
+                .iter()
+                .map(|model| {
+                    let connection_id = model.connection_id.clone().unwrap_or_default();
+                    (connection_id, model.name.clone(), model.size_vram)
+                })
+                .collect();
+            key.sort();
+            if previous.as_ref() != Some(&key) {
+                let _ = app.emit("running_models_changed", &models);
+                previous = Some(key);
+            }
+        }
+    });
 }
 
 /// Send a chat message and get response
@@ -379,239 +743,341 @@ pub async fn send_message(
     backend: Option<String>,
     backend_type: Option<String>,
     backend_session_id: Option<String>,
+    connection_id: Option<String>,
+    tool_calling_enabled: Option<bool>,
+    max_tool_calls: Option<u32>,
+    enabled_tools: Option<Vec<String>>,
+    conversation_id: Option<String>,
+    project_id: Option<String>,
+    context_strategy: Option<ContextStrategy>,
+    summarization: Option<SummarizationConfig>,
+    summary: Option<ConversationSummary>,
+    think: Option<bool>,
+    keep_alive: Option<String>,
+    response_format: Option<ResponseFormat>,
 ) -> Result<serde_json::Value, String> {
-    let active_backend = get_active_backend(&state)?;
-    let backend_kind = BackendKind::parse(backend.as_deref().or(backend_type.as_deref()))
-        .unwrap_or(active_backend);
-
-    match backend_kind {
-        BackendKind::Ollama => {
-            let ollama = get_ollama_client(&state)?;
-            let params = parameters.unwrap_or_default();
-
-            let ollama_messages = messages_to_ollama(&messages, system_prompt.as_deref());
-
-            let request = OllamaChatRequest {
-                model,
-                messages: ollama_messages,
-                stream: stream.or(Some(false)),
-                options: Some(OllamaOptions {
-                    temperature: Some(params.temperature),
-                    top_k: params.top_k,
-                    top_p: params.top_p,
-                    num_predict: params.max_tokens,
-                }),
-            };
+    let (connection_id, client) = resolve_connection(
+        &state,
+        connection_id.as_deref(),
+        backend.as_deref().or(backend_type.as_deref()),
+    )?;
+
+    let data_dir = app_data_dir(&app)?;
+    let provider = client.kind().key();
+    state
+        .usage
+        .check_budget(&data_dir, &connection_id, provider, &model)?;
+    let usage_record = UsageRecord {
+        timestamp: Utc::now(),
+        connection_id: connection_id.clone(),
+        provider: provider.to_string(),
+        model: model.clone(),
+        project_id,
+        conversation_id: conversation_id.clone(),
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        cost: 0.0,
+        cancelled: false,
+    };
 
-            if stream.unwrap_or(false) {
-                let mut full_content = String::new();
-                let mut eval_count = 0u32;
-                let mut total_duration = 0u64;
-
-                let request_id_for_emit = request_id.clone();
-
-                let streaming_result = ollama.chat_stream_with_callback(&request, |chunk| {
-                    if let Some(msg) = &chunk.message {
-                        full_content.push_str(&msg.content);
-                        if let Some(req_id) = &request_id_for_emit {
-                            let _ = app.emit("chat_stream_chunk", serde_json::json!({
-                                "requestId": req_id,
-                                "content": msg.content,
-                                "done": false,
-                            }));
-                        }
-                    }
+    // Registered before any work, so a cancel also stops summarising and
+    // context planning
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    if let Some(req_id) = &request_id {
+        let mut active_requests = state.active_requests.lock().map_err(|e| e.to_string())?;
+        active_requests.insert(req_id.clone(), abort_handle);
+    }
 
-                    if let Some(ec) = chunk.eval_count {
-                        eval_count = ec;
-                    }
-                    if let Some(td) = chunk.total_duration {
-                        total_duration = td;
-                    }
-                }).await;
+    let mut session_id = session_id
+        .or(backend_session_id)
+        .filter(|id| !id.trim().is_empty());
+    let summarization = summarization.filter(|config| config.enabled);
+    let mut summary = summary;
+    let mut summary_updated = false;
+    let mut summary_error = None;
+    let mut streaming = false;
+    // Estimated prompt size, known once the context is planned
+    let mut prompt_tokens = None;
+    let mut partial_content = String::new();
+    let mut partial_reasoning = String::new();
+
+    let pipeline = async {
+        let mut tool_context = tool_context(&app, &state)?;
+        if let Some(max_tool_calls) = max_tool_calls {
+            tool_context.config.max_tool_calls = max_tool_calls;
+        }
 
-                if let Err(error) = streaming_result {
-                    let error_message = error.to_string();
+        // Session backends get their session up front, so a cancelled first
+        // message can still be aborted on the server
+        if session_id.is_none() && client.capabilities().sessions {
+            session_id = Some(client.create_session().await?);
+        }
 
-                    if let Some(req_id) = request_id {
-                        let _ = app.emit("chat_stream_chunk", serde_json::json!({
+        let mut request = BackendChatRequest {
+            model,
+            messages: crate::tools::expand_tool_history(messages),
+            system_prompt,
+            parameters: parameters.unwrap_or_default(),
+            session_id: session_id.clone(),
+            tools: if tool_calling_enabled.unwrap_or(false) && client.capabilities().tools {
+                state
+                    .tools
+                    .enabled_definitions(enabled_tools.as_deref(), &tool_context.config)
+            } else {
+                Vec::new()
+            },
+            think,
+            keep_alive: keep_alive.filter(|keep_alive| !keep_alive.trim().is_empty()),
+            response_format: None,
+        };
+
+        // An invalid schema fails here rather than after the model has run
+        let structured_output = match &response_format {
+            Some(format) => {
+                request.system_prompt =
+                    Some(structured::with_instruction(request.system_prompt.take(), format));
+                Some(StructuredOutput::new(format)?)
+            }
+            None => None,
+        };
+        request.response_format = response_format;
+
+        // Rolling summary first, so the context planner sees the shortened
+        // history. Backend-side sessions already hold the history, so the chat
+        // backend's capabilities decide, not the summarizer's.
+        let in_session = request.session_id.is_some() && client.capabilities().sessions;
+        if let Some(config) = summarization.as_ref().filter(|_| !in_session) {
+            let summarizer = match config.connection_id.as_deref() {
+                Some(id) if id != connection_id => get_backend(&state, Some(id)),
+                _ => Ok(client.clone()),
+            };
+            let summarizer_model = config
+                .model
+                .clone()
+                .filter(|model| !model.trim().is_empty())
+                .unwrap_or_else(|| request.model.clone());
+            let result = match summarizer {
+                Ok(summarizer) => {
+                    crate::summary::apply_rolling_summary(
+                        summarizer.as_ref(),
+                        &summarizer_model,
+                        config,
+                        &mut summary,
+                        &mut request,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(updated) => summary_updated = updated,
+                // The chat goes ahead with the previous summary, including when
+                // the summarizer connection is gone
+                Err(error) => summary_error = Some(error),
+            }
+            if let (true, Some(req_id)) = (summary_updated, &request_id) {
+                let _ = app.emit("conversation_summary_updated", serde_json::json!({
+                    "requestId": req_id,
+                    "summary": &summary,
+                }));
+            }
+        }
+
+        let context = state
+            .context
+            .plan(
+                client.as_ref(),
+                &connection_id,
+                conversation_id.as_deref(),
+                &mut request,
+                context_strategy.unwrap_or_default(),
+            )
+            .await?;
+        prompt_tokens = Some(context.estimated_tokens);
+        if let (false, Some(req_id)) = (context.dropped_message_ids.is_empty(), &request_id) {
+            let _ = app.emit("context_truncated", serde_json::json!({
+                "requestId": req_id,
+                "context": &context,
+            }));
+        }
+
+        streaming = stream.unwrap_or(false) && client.capabilities().streaming;
+
+        let mut on_chunk = |chunk: StreamChunk<'_>| {
+            let (content, reasoning, tool_call) = match chunk {
+                StreamChunk::Content(text) => {
+                    partial_content.push_str(text);
+                    (text, None, None)
+                }
+                StreamChunk::Reasoning(text) => {
+                    partial_reasoning.push_str(text);
+                    ("", Some(text), None)
+                }
+                StreamChunk::ToolCall(call) => ("", None, Some(call)),
+                StreamChunk::PermissionRequest(permission) => {
+                    if let Some(req_id) = &request_id {
+                        let _ = app.emit("opencode_permission_request", serde_json::json!({
                             "requestId": req_id,
-                            "content": "",
-                            "done": true,
-                            "error": &error_message,
+                            "permission": permission,
                         }));
                     }
-
-                    return Err(error_message);
+                    return;
                 }
+            };
+            if let Some(req_id) = &request_id {
+                let _ = app.emit("chat_stream_chunk", serde_json::json!({
+                    "requestId": req_id,
+                    "content": content,
+                    "reasoning": reasoning,
+                    "toolCall": tool_call,
+                    "done": false,
+                }));
+            }
+        };
 
-                if let Some(req_id) = request_id {
-                    let _ = app.emit("chat_stream_chunk", serde_json::json!({
-                        "requestId": req_id,
-                        "content": "",
-                        "done": true,
-                        "evalCount": eval_count,
-                        "totalDuration": total_duration,
-                    }));
-                }
+        let mut on_tool_call = |call: &ToolCall| {
+            if let Some(req_id) = &request_id {
+                let _ = app.emit("tool_call_status", serde_json::json!({
+                    "requestId": req_id,
+                    "toolCall": call,
+                }));
+            }
+        };
 
-                Ok(serde_json::json!({
-                    "content": full_content,
-                    "eval_count": eval_count,
-                    "total_duration": total_duration,
-                    "done": true,
-                }))
-            } else {
-                let response = ollama.chat(&request).await?;
-                let content = response.message
-                    .map(|m| m.content)
-                    .unwrap_or_default();
+        let response = state
+            .tools
+            .chat_with_tools(
+                client.as_ref(),
+                request,
+                streaming,
+                &tool_context,
+                &mut on_chunk,
+                &mut on_tool_call,
+            )
+            .await?;
+        Ok::<_, String>((response, context, structured_output))
+    };
+    let result = Abortable::new(pipeline, abort_registration).await;
 
-                Ok(serde_json::json!({
-                    "content": content,
-                    "eval_count": response.eval_count,
-                    "total_duration": response.total_duration,
+    if let Some(req_id) = &request_id {
+        let mut active_requests = state.active_requests.lock().map_err(|e| e.to_string())?;
+        active_requests.remove(req_id);
+    }
+
+    let (mut response, context, structured_output) = match result {
+        Ok(Ok(done)) => done,
+        Ok(Err(error)) => {
+            if let (true, Some(req_id)) = (streaming, &request_id) {
+                let _ = app.emit("chat_stream_chunk", serde_json::json!({
+                    "requestId": req_id,
+                    "content": "",
                     "done": true,
-                }))
+                    "error": &error,
+                }));
             }
+
+            return Err(error);
         }
-        BackendKind::Opencode => {
-            let opencode = get_opencode_client(&state)?;
-            let user_text = messages
-                .iter()
-                .rev()
-                .find(|message| message.role == MessageRole::User)
-                .map(|message| message.content.trim().to_string())
-                .unwrap_or_default();
+        Err(Aborted) => {
+            // Dropping the request only stops reading; OpenCode keeps
+            // generating until its session is aborted
+            if let (true, Some(session_id)) = (client.capabilities().sessions, &session_id) {
+                let _ = client.abort_session(session_id).await;
+            }
 
-            if user_text.is_empty() {
-                return Err("No user message found to send".to_string());
+            // Cloud providers bill cancelled generations too; the backend never
+            // reported token counts, so estimate them from what was sent and streamed
+            if let Some(prompt_tokens) = prompt_tokens {
+                let completion_tokens = crate::context::estimate_tokens(&partial_content)
+                    + crate::context::estimate_tokens(&partial_reasoning);
+                let _ = state.usage.record(
+                    &data_dir,
+                    UsageRecord {
+                        cancelled: true,
+                        ..usage_record
+                    },
+                    Some(prompt_tokens),
+                    Some(completion_tokens),
+                    None,
+                );
             }
 
-            let model_selection = parse_model_selector(&model);
-            let resolved_session_id = match session_id.or(backend_session_id) {
-                Some(id) if !id.trim().is_empty() => id,
-                _ => opencode.create_session(None).await?,
-            };
+            if let Some(req_id) = &request_id {
+                let _ = app.emit("chat_stream_chunk", serde_json::json!({
+                    "requestId": req_id,
+                    "content": "",
+                    "done": true,
+                    "cancelled": true,
+                    "partialContent": &partial_content,
+                    "partialReasoning": &partial_reasoning,
+                }));
+            }
 
-            let response = opencode
-                .prompt_session(
-                    &resolved_session_id,
-                    &user_text,
-                    model_selection.as_ref(),
-                    system_prompt.as_deref(),
-                )
-                .await?;
-
-            Ok(serde_json::json!({
-                "content": response.content,
+            return Ok(serde_json::json!({
+                "content": partial_content,
+                "reasoning": Some(partial_reasoning).filter(|reasoning| !reasoning.is_empty()),
+                "session_id": session_id,
+                "connection_id": connection_id,
                 "done": true,
-                "session_id": resolved_session_id,
-            }))
+                "cancelled": true,
+            }));
         }
-        BackendKind::LmStudio => {
-            let lm_studio = get_lm_studio_client(&state)?;
-            let params = parameters.unwrap_or_default();
-            let previous_response_id = session_id
-                .or(backend_session_id)
-                .filter(|id| !id.trim().is_empty());
-            let prompt = if previous_response_id.is_some() {
-                latest_user_text(&messages).unwrap_or_default()
-            } else {
-                build_lm_studio_prompt(&messages)
-            };
+    };
 
-            if prompt.trim().is_empty() {
-                return Err("No user message found to send".to_string());
-            }
+    // A failed ledger write must not discard a completed response
+    if let Ok(cost) = state.usage.record(
+        &data_dir,
+        usage_record,
+        response.prompt_eval_count,
+        response.eval_count,
+        response.cost,
+    ) {
+        response.cost = cost;
+    }
 
-            let attachments = latest_user_attachments(&messages);
-            let model_id = strip_lm_studio_prefix(&model).to_string();
-
-            if stream.unwrap_or(true) {
-                let mut full_content = String::new();
-                let request_id_for_emit = request_id.clone();
-
-                let response = lm_studio
-                    .chat_stream_with_callback(
-                        &model_id,
-                        &prompt,
-                        &attachments,
-                        system_prompt.as_deref(),
-                        params.temperature,
-                        params.max_tokens,
-                        previous_response_id.as_deref(),
-                        |chunk| {
-                            full_content.push_str(chunk);
-                            if let Some(req_id) = &request_id_for_emit {
-                                let _ = app.emit("chat_stream_chunk", serde_json::json!({
-                                    "requestId": req_id,
-                                    "content": chunk,
-                                    "done": false,
-                                }));
-                            }
-                        },
-                    )
-                    .await;
-
-                let response = match response {
-                    Ok(response) => response,
-                    Err(error) => {
-                        if let Some(req_id) = request_id {
-                            let _ = app.emit("chat_stream_chunk", serde_json::json!({
-                                "requestId": req_id,
-                                "content": "",
-                                "done": true,
-                                "error": &error,
-                            }));
-                        }
-
-                        return Err(error);
-                    }
-                };
-
-                let session_id = response.response_id;
-                let final_content = if full_content.is_empty() {
-                    response.content
-                } else {
-                    full_content
-                };
-
-                if let Some(req_id) = request_id {
-                    let _ = app.emit("chat_stream_chunk", serde_json::json!({
-                        "requestId": req_id,
-                        "content": "",
-                        "done": true,
-                    }));
-                }
+    if let (true, Some(req_id)) = (streaming, &request_id) {
+        let _ = app.emit("chat_stream_chunk", serde_json::json!({
+            "requestId": req_id,
+            "content": "",
+            "done": true,
+            "promptEvalCount": response.prompt_eval_count,
+            "evalCount": response.eval_count,
+            "totalDuration": response.total_duration,
+            "cost": response.cost,
+        }));
+    }
 
-                Ok(serde_json::json!({
-                    "content": final_content,
-                    "done": true,
-                    "session_id": session_id,
-                }))
-            } else {
-                let response = lm_studio
-                    .chat(
-                        &model_id,
-                        &prompt,
-                        &attachments,
-                        system_prompt.as_deref(),
-                        params.temperature,
-                        params.max_tokens,
-                        previous_response_id.as_deref(),
-                    )
-                    .await?;
-                let session_id = response.response_id;
-                let content = response.content;
+    let mut result = chat_response_json(&connection_id, &response);
+    result["context"] = serde_json::json!(context);
+    if let Some(structured_output) = &structured_output {
+        let (structured, error) = match structured_output.parse(&response.content) {
+            Ok(value) => (Some(value), None),
+            Err(error) => (None, Some(error)),
+        };
+        result["structured"] = serde_json::json!(structured);
+        result["structured_error"] = serde_json::json!(error);
+    }
+    if summarization.is_some() {
+        result["summary"] = serde_json::json!(summary);
+        result["summary_updated"] = serde_json::json!(summary_updated);
+        result["summary_error"] = serde_json::json!(summary_error);
+    }
+    Ok(result)
+}
 
-                Ok(serde_json::json!({
-                    "content": content,
-                    "done": true,
-                    "session_id": session_id,
-                }))
-            }
+/// Abort an in-flight `send_message` call.
+///
+/// Dropping the request future stops reading the stream and closes the HTTP
+/// connection; `send_message` then emits a final `cancelled` chunk.
+#[tauri::command]
+pub fn cancel_message(state: State<'_, AppState>, request_id: String) -> Result<bool, String> {
+    let mut active_requests = state.active_requests.lock().map_err(|e| e.to_string())?;
+    match active_requests.remove(&request_id) {
+        Some(handle) => {
+            handle.abort();
+            Ok(true)
         }
+        None => Ok(false),
     }
 }
 
@@ -621,113 +1087,165 @@ pub async fn generate_title(
     state: State<'_, AppState>,
     model: String,
     first_message: String,
+    connection_id: Option<String>,
 ) -> Result<String, String> {
-    if get_active_backend(&state)? != BackendKind::Ollama {
-        return Ok(simple_title(&first_message));
-    }
+    let backend = get_backend(&state, connection_id.as_deref())?;
+    backend.generate_title(&model, &first_message).await
+}
 
-    let ollama = get_ollama_client(&state)?;
-    ollama.generate_title(&model, &first_message).await
+/// List the sessions a backend holds, e.g. OpenCode sessions started from
+/// the terminal
+#[tauri::command]
+pub async fn list_sessions(
+    state: State<'_, AppState>,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<Vec<BackendSession>, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.list_sessions().await
 }
 
-/// Fetch a web page and return its text content
+/// Load the full history of a backend session, to attach it to a conversation
 #[tauri::command]
-pub async fn fetch_webpage(url: String) -> Result<serde_json::Value, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (compatible; PrivateChatHub/0.1)")
-        .build()
-        .map_err(|e| e.to_string())?;
+pub async fn get_session_messages(
+    state: State<'_, AppState>,
+    session_id: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<Vec<Message>, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.session_messages(&session_id).await
+}
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch URL: {}", e))?;
-
-    let status = response.status().as_u16();
-    let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("unknown")
-        .to_string();
-
-    let body = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response body: {}", e))?;
+/// Stop a session that is still running, e.g. an agent started elsewhere
+#[tauri::command]
+pub async fn abort_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<bool, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.abort_session(&session_id).await
+}
 
-    let text = if content_type.contains("text/html") {
-        strip_html_tags(&body)
-    } else {
-        body
-    };
+#[tauri::command]
+pub async fn delete_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<(), String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.delete_session(&session_id).await
+}
 
-    let length = text.len();
+/// Share a session publicly; the returned session carries the share link
+#[tauri::command]
+pub async fn share_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<BackendSession, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.share_session(&session_id, true).await
+}
 
-    Ok(serde_json::json!({
-        "url": url,
-        "status": status,
-        "content_type": content_type,
-        "content": text,
-        "length": length,
-    }))
+#[tauri::command]
+pub async fn unshare_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<BackendSession, String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.share_session(&session_id, false).await
 }
 
-/// Simple HTML tag stripper
-fn strip_html_tags(html: &str) -> String {
-    let mut result = String::with_capacity(html.len());
-    let mut in_tag = false;
-    let mut in_script = false;
-    let mut in_style = false;
-    let mut last_was_whitespace = false;
-
-    let lower = html.to_lowercase();
-    let chars: Vec<char> = html.chars().collect();
-    let lower_chars: Vec<char> = lower.chars().collect();
-
-    let mut i = 0;
-    while i < chars.len() {
-        if !in_tag && i + 7 < lower_chars.len() && &lower[i..i + 7] == "<script" {
-            in_script = true;
-            in_tag = true;
-        } else if !in_tag && i + 6 < lower_chars.len() && &lower[i..i + 6] == "<style" {
-            in_style = true;
-            in_tag = true;
-        } else if in_script && i + 9 <= lower_chars.len() && &lower[i..i + 9] == "</script>" {
-            in_script = false;
-            i += 9;
-            continue;
-        } else if in_style && i + 8 <= lower_chars.len() && &lower[i..i + 8] == "</style>" {
-            in_style = false;
-            i += 8;
-            continue;
-        } else if chars[i] == '<' {
-            in_tag = true;
-        } else if chars[i] == '>' {
-            in_tag = false;
-            i += 1;
-            continue;
-        }
+/// Compact a backend session's history into a summary written by `model`
+#[tauri::command]
+pub async fn summarize_session(
+    state: State<'_, AppState>,
+    session_id: String,
+    model: String,
+    connection_id: Option<String>,
+    backend: Option<String>,
+) -> Result<(), String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), backend.as_deref())?;
+    client.summarize_session(&session_id, &model).await
+}
 
-        if !in_tag && !in_script && !in_style {
-            let ch = chars[i];
-            if ch.is_whitespace() {
-                if !last_was_whitespace {
-                    result.push(' ');
-                    last_was_whitespace = true;
-                }
-            } else {
-                result.push(ch);
-                last_was_whitespace = false;
-            }
-        }
+/// Answer an agent's permission request, letting a paused session continue
+#[tauri::command]
+pub async fn respond_opencode_permission(
+    state: State<'_, AppState>,
+    session_id: String,
+    permission_id: String,
+    response: PermissionResponse,
+    connection_id: Option<String>,
+) -> Result<(), String> {
+    let (_, client) = resolve_connection(&state, connection_id.as_deref(), Some("opencode"))?;
+    client
+        .respond_permission(&session_id, &permission_id, response)
+        .await
+}
 
-        i += 1;
-    }
+/// List the tools models can call, for per-conversation enable/disable
+#[tauri::command]
+pub fn list_tools(state: State<'_, AppState>) -> Vec<ToolDefinition> {
+    state.tools.definitions()
+}
 
-    result.trim().to_string()
+/// Replace the tool settings used by tool calls and web tools
+#[tauri::command]
+pub fn update_tool_config(state: State<'_, AppState>, config: ToolConfig) -> Result<(), String> {
+    let mut tool_config = state.tool_config.lock().map_err(|e| e.to_string())?;
+    *tool_config = config;
+    Ok(())
+}
+
+/// Fetch a web page and return its main content as Markdown
+#[tauri::command]
+pub async fn fetch_webpage(
+    state: State<'_, AppState>,
+    url: String,
+) -> Result<serde_json::Value, String> {
+    let config = state.tool_config.lock().map_err(|e| e.to_string())?.clone();
+    crate::tools::fetch_webpage(&url, &config).await
+}
+
+/// Search the web with the configured provider
+#[tauri::command]
+pub async fn web_search(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    query: String,
+    max_results: Option<u32>,
+) -> Result<serde_json::Value, String> {
+    let context = tool_context(&app, &state)?;
+    crate::tools::web_search(&query, max_results, &context).await
+}
+
+/// Aggregate token usage and spend by model, provider, project and day
+#[tauri::command]
+pub fn get_usage_report(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<UsageReport, String> {
+    state.usage.report(&app_data_dir(&app)?, since, until)
+}
+
+/// Set the monthly spend limits that block requests to paid models
+#[tauri::command]
+pub fn set_usage_budget(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    budget: UsageBudget,
+) -> Result<(), String> {
+    state.usage.set_budget(&app_data_dir(&app)?, budget)
 }
 
 /// Compare two models with the same prompt
@@ -739,44 +1257,39 @@ pub async fn compare_models(
     messages: Vec<Message>,
     system_prompt: Option<String>,
     parameters: Option<ModelParameters>,
+    connection_id: Option<String>,
 ) -> Result<serde_json::Value, String> {
-    if get_active_backend(&state)? != BackendKind::Ollama {
-        return Err("Model comparison is only available with Ollama backend".to_string());
-    }
-
-    let ollama = get_ollama_client(&state)?;
-    let params = parameters.unwrap_or_default();
-    let ollama_messages = messages_to_ollama(&messages, system_prompt.as_deref());
+    let backend = get_backend(&state, connection_id.as_deref())?;
 
-    let request1 = OllamaChatRequest {
+    let request1 = BackendChatRequest {
         model: model1.clone(),
-        messages: ollama_messages.clone(),
-        stream: Some(false),
-        options: Some(OllamaOptions {
-            temperature: Some(params.temperature),
-            top_k: params.top_k,
-            top_p: params.top_p,
-            num_predict: params.max_tokens,
-        }),
+        messages,
+        system_prompt,
+        parameters: parameters.unwrap_or_default(),
+        session_id: None,
+        tools: Vec::new(),
+        think: None,
+        keep_alive: None,
+        response_format: None,
     };
 
     let mut request2 = request1.clone();
     request2.model = model2.clone();
 
     let start1 = std::time::Instant::now();
-    let response1 = ollama.chat(&request1).await;
+    let response1 = backend.chat(&request1).await;
     let duration1 = start1.elapsed().as_millis() as u64;
 
     let start2 = std::time::Instant::now();
-    let response2 = ollama.chat(&request2).await;
+    let response2 = backend.chat(&request2).await;
     let duration2 = start2.elapsed().as_millis() as u64;
 
     let content1 = response1
-        .map(|r| r.message.map(|m| m.content).unwrap_or_default())
+        .map(|r| r.content)
         .unwrap_or_else(|e| format!("Error: {}", e));
 
     let content2 = response2
-        .map(|r| r.message.map(|m| m.content).unwrap_or_default())
+        .map(|r| r.content)
         .unwrap_or_else(|e| format!("Error: {}", e));
 
     Ok(serde_json::json!({
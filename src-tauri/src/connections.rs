@@ -0,0 +1,410 @@
+use crate::backend::{create_backend, BackendCapabilities, BackendKind, ChatBackend};
+use crate::models::{CloudProvider, Connection};
+use crate::secrets;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// File in the app data directory holding the registered connections
+pub const CONNECTIONS_FILE: &str = "connections.json";
+
+/// File in the app data directory holding the configured cloud providers
+pub const CLOUD_PROVIDERS_FILE: &str = "cloud_providers.json";
+
+/// Local servers registered until the user configures their own connections
+const LOCAL_DEFAULTS: [(&str, &str, BackendKind, u16); 3] = [
+    ("local-ollama", "Local Ollama", BackendKind::Ollama, 11434),
+    (
+        "local-opencode",
+        "OpenCode Server",
+        BackendKind::Opencode,
+        4096,
+    ),
+    ("local-lmstudio", "LM Studio", BackendKind::LmStudio, 1234),
+];
+
+/// A configured connection together with its live client
+pub struct ConnectionEntry {
+    pub connection: Connection,
+    pub kind: BackendKind,
+    pub backend: Arc<dyn ChatBackend>,
+    pub healthy: bool,
+}
+
+/// Connection details reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+    #[serde(flatten)]
+    pub connection: Connection,
+    pub healthy: bool,
+    pub capabilities: BackendCapabilities,
+}
+
+/// A connection as written to disk. `Connection` never serializes its secrets;
+/// they live in the keychain and only their accounts are stored here.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredConnection {
+    #[serde(flatten)]
+    connection: Connection,
+    #[serde(default)]
+    password_ref: Option<String>,
+    #[serde(default)]
+    api_token_ref: Option<String>,
+}
+
+/// A cloud provider as written to disk, with its API key in the keychain
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredCloudProvider {
+    #[serde(flatten)]
+    provider: CloudProvider,
+    #[serde(default)]
+    api_key_ref: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct StoredConnections {
+    default_id: Option<String>,
+    connections: Vec<StoredConnection>,
+}
+
+/// Registry of named backend connections keyed by connection id.
+///
+/// Several connections (of the same or different backend kinds) can be
+/// registered at once; commands pick one by id, falling back to the default.
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    entries: HashMap<String, ConnectionEntry>,
+    default_id: Option<String>,
+}
+
+impl ConnectionRegistry {
+    /// Connections to the local Ollama, OpenCode and LM Studio servers on their
+    /// default ports, with Ollama as the default
+    pub fn with_local_defaults() -> Self {
+        let mut registry = Self::default();
+        for (id, name, kind, port) in LOCAL_DEFAULTS {
+            let connection = Connection {
+                id: id.to_string(),
+                name: name.to_string(),
+                backend: kind.key().to_string(),
+                port,
+                is_default: kind == BackendKind::Ollama,
+                ..Connection::default()
+            };
+            // Local clients only fail to build on an invalid URL, never here
+            let _ = registry.upsert(connection);
+        }
+        registry
+    }
+
+    /// Load the registry saved in `data_dir`, or the local defaults when none
+    /// has been saved yet
+    pub fn load(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(CONNECTIONS_FILE);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::with_local_defaults());
+            }
+            Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+        };
+        let stored: StoredConnections = match serde_json::from_slice(&bytes) {
+            Ok(stored) => stored,
+            Err(_) => {
+                // Keep the unreadable file for inspection instead of overwriting it
+                let corrupt_path = path.with_extension("json.corrupt");
+                fs::rename(&path, &corrupt_path)
+                    .map_err(|error| format!("Failed to move aside {}: {error}", path.display()))?;
+                return Ok(Self::with_local_defaults());
+            }
+        };
+
+        // Connections whose client cannot be built are dropped
+        let mut registry = Self::default();
+        for stored in stored.connections {
+            let mut connection = stored.connection;
+            connection.password = secrets::load(stored.password_ref.as_deref());
+            connection.api_token = secrets::load(stored.api_token_ref.as_deref());
+            let _ = registry.upsert(connection);
+        }
+        if let Some(id) = stored
+            .default_id
+            .filter(|id| registry.entries.contains_key(id))
+        {
+            registry.set_default(&id);
+        }
+        Ok(registry)
+    }
+
+    /// Write every connection to `data_dir`, storing its secrets in the keychain
+    pub fn save(&self, data_dir: &Path) -> Result<(), String> {
+        let mut connections = self
+            .entries
+            .values()
+            .map(|entry| {
+                let connection = &entry.connection;
+                Ok(StoredConnection {
+                    password_ref: secrets::save(
+                        connection_secret(&connection.id, "password"),
+                        connection.password.as_deref(),
+                    )?,
+                    api_token_ref: secrets::save(
+                        connection_secret(&connection.id, "api_token"),
+                        connection.api_token.as_deref(),
+                    )?,
+                    connection: connection.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        connections.sort_by_key(|stored| stored.connection.created_at);
+        let stored = StoredConnections {
+            default_id: self.default_id.clone(),
+            connections,
+        };
+
+        write_json(data_dir, CONNECTIONS_FILE, &stored)
+    }
+
+    /// Add or replace a connection, rebuilding its client
+    pub fn upsert(&mut self, connection: Connection) -> Result<Arc<dyn ChatBackend>, String> {
+        let backend = create_backend(&connection)?;
+        let kind = backend.kind();
+        let id = connection.id.clone();
+        let is_default = connection.is_default;
+
+        self.entries.insert(
+            id.clone(),
+            ConnectionEntry {
+                connection,
+                kind,
+                backend: backend.clone(),
+                healthy: false,
+            },
+        );
+
+        if is_default || self.default_id.is_none() {
+            self.set_default(&id);
+        }
+
+        Ok(backend)
+    }
+
+    /// Remove a connection and its stored secrets
+    pub fn remove(&mut self, id: &str) -> bool {
+        let removed = self.entries.remove(id).is_some();
+        if removed {
+            secrets::delete(&connection_secret(id, "password"));
+            secrets::delete(&connection_secret(id, "api_token"));
+        }
+        if self.default_id.as_deref() == Some(id) {
+            // The oldest remaining connection takes over as the default
+            let next = self
+                .entries
+                .values()
+                .min_by_key(|entry| entry.connection.created_at)
+                .map(|entry| entry.connection.id.clone());
+            match next {
+                Some(next) => self.set_default(&next),
+                None => self.default_id = None,
+            }
+        }
+        removed
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ConnectionEntry> {
+        self.entries.get(id)
+    }
+
+    pub fn default_id(&self) -> Option<&str> {
+        self.default_id.as_deref()
+    }
+
+    pub fn set_default(&mut self, id: &str) {
+        self.default_id = Some(id.to_string());
+        for entry in self.entries.values_mut() {
+            entry.connection.is_default = entry.connection.id == id;
+        }
+    }
+
+    /// Find the first registered connection of a backend kind, preferring the default
+    pub fn find_by_kind(&self, kind: BackendKind) -> Option<&ConnectionEntry> {
+        if let Some(entry) = self
+            .default_id
+            .as_deref()
+            .and_then(|id| self.entries.get(id))
+        {
+            if entry.kind == kind {
+                return Some(entry);
+            }
+        }
+
+        let mut matches = self
+            .entries
+            .values()
+            .filter(|entry| entry.kind == kind)
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|entry| entry.connection.created_at);
+        matches.into_iter().next()
+    }
+
+    /// Resolve the connection a command should use.
+    ///
+    /// An explicit connection id wins; otherwise a backend kind selects the
+    /// matching connection, and with neither the default connection is used.
+    pub fn resolve(
+        &self,
+        connection_id: Option<&str>,
+        kind: Option<BackendKind>,
+    ) -> Result<(String, Arc<dyn ChatBackend>), String> {
+        let connection_id = connection_id.map(str::trim).filter(|id| !id.is_empty());
+
+        let entry = match (connection_id, kind) {
+            (Some(id), _) => self
+                .entries
+                .get(id)
+                .ok_or_else(|| format!("Unknown connection: {id}"))?,
+            (None, Some(kind)) => self
+                .find_by_kind(kind)
+                .ok_or_else(|| format!("No {} connection is configured", kind.label()))?,
+            (None, None) => self
+                .default_id
+                .as_deref()
+                .and_then(|id| self.entries.get(id))
+                .ok_or_else(|| "No connection is configured".to_string())?,
+        };
+
+        Ok((entry.connection.id.clone(), entry.backend.clone()))
+    }
+
+    /// Clients for every registered connection, keyed by connection id
+    pub fn backends(&self) -> Vec<(String, Arc<dyn ChatBackend>)> {
+        self.entries
+            .iter()
+            .map(|(id, entry)| (id.clone(), entry.backend.clone()))
+            .collect()
+    }
+
+    pub fn set_health(&mut self, id: &str, healthy: bool) {
+        if let Some(entry) = self.entries.get_mut(id) {
+            entry.healthy = healthy;
+            if healthy {
+                entry.connection.last_connected_at = Some(Utc::now());
+            }
+        }
+    }
+
+    pub fn status(&self, id: &str) -> Option<ConnectionStatus> {
+        self.entries.get(id).map(|entry| ConnectionStatus {
+            connection: entry.connection.clone(),
+            healthy: entry.healthy,
+            capabilities: entry.backend.capabilities(),
+        })
+    }
+
+    pub fn statuses(&self) -> Vec<ConnectionStatus> {
+        let mut statuses = self
+            .entries
+            .keys()
+            .filter_map(|id| self.status(id))
+            .collect::<Vec<_>>();
+        statuses.sort_by_key(|status| status.connection.created_at);
+        statuses
+    }
+}
+
+/// Keychain account of a connection's secret
+fn connection_secret(id: &str, field: &str) -> String {
+    format!("connection/{id}/{field}")
+}
+
+/// Keychain account of a cloud provider's API key
+fn cloud_provider_secret(id: &str) -> String {
+    format!("cloud_provider/{id}/api_key")
+}
+
+/// Cloud providers saved in `data_dir`, with their models and prices.
+///
+/// An unreadable file is moved aside and no providers are restored, so a
+/// corrupt file never stops the app from starting.
+pub fn read_cloud_providers(data_dir: &Path) -> Result<Vec<CloudProvider>, String> {
+    let path = data_dir.join(CLOUD_PROVIDERS_FILE);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+    };
+    match serde_json::from_slice::<Vec<StoredCloudProvider>>(&bytes) {
+        Ok(stored) => Ok(stored
+            .into_iter()
+            .map(|stored| CloudProvider {
+                api_key: secrets::load(stored.api_key_ref.as_deref()),
+                ..stored.provider
+            })
+            .collect()),
+        Err(error) => {
+            // Keep the unreadable file for inspection instead of overwriting it
+            let corrupt_path = path.with_extension("json.corrupt");
+            match fs::rename(&path, &corrupt_path) {
+                Ok(()) => eprintln!(
+                    "Warning: {} is corrupt ({error}); moved it to {}",
+                    path.display(),
+                    corrupt_path.display()
+                ),
+                Err(rename_error) => eprintln!(
+                    "Warning: {} is corrupt ({error}) and could not be moved aside: \
+                     {rename_error}",
+                    path.display()
+                ),
+            }
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Write the cloud providers to `data_dir`, storing their API keys in the
+/// keychain
+pub fn write_cloud_providers(data_dir: &Path, providers: &[CloudProvider]) -> Result<(), String> {
+    let stored = providers
+        .iter()
+        .map(|provider| {
+            Ok(StoredCloudProvider {
+                api_key_ref: secrets::save(
+                    cloud_provider_secret(&provider.id),
+                    provider.api_key.as_deref(),
+                )?,
+                provider: CloudProvider {
+                    api_key: None,
+                    ..provider.clone()
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    write_json(data_dir, CLOUD_PROVIDERS_FILE, &stored)
+}
+
+/// Remove the stored API key of a deleted cloud provider
+pub fn delete_cloud_provider_secret(id: &str) {
+    secrets::delete(&cloud_provider_secret(id));
+}
+
+/// Write a file in `data_dir` through a temporary file, so a crash never
+/// leaves it half-written
+fn write_json(data_dir: &Path, file_name: &str, value: &impl Serialize) -> Result<(), String> {
+    fs::create_dir_all(data_dir)
+        .map_err(|error| format!("Failed to create {}: {error}", data_dir.display()))?;
+    let path = data_dir.join(file_name);
+    let temp_path = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(value)
+        .map_err(|error| format!("Failed to serialize {file_name}: {error}"))?;
+    fs::write(&temp_path, bytes)
+        .map_err(|error| format!("Failed to write {}: {error}", temp_path.display()))?;
+    fs::rename(&temp_path, &path)
+        .map_err(|error| format!("Failed to finalize {}: {error}", path.display()))
+}
@@ -0,0 +1,427 @@
+//! Fits conversation history into a model's context window.
+//!
+//! Token counts are estimated from text length, so the planner leaves room
+//! for the response and drops whole turns: an assistant message that called
+//! tools is always kept or dropped together with its tool results.
+
+use crate::backend::{BackendChatRequest, BackendKind, ChatBackend, SUMMARY_MAX_TOKENS};
+use crate::models::{CloudModel, ContextStrategy, Message, MessageRole};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Rough average for English text and code across common tokenizers
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens spent on role markers and separators for each message
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// Flat estimate for an image attachment
+const IMAGE_TOKENS: u32 = 768;
+
+/// Room left for the response when the request sets no `max_tokens`
+const DEFAULT_RESPONSE_TOKENS: u32 = 1024;
+
+/// Outcome of fitting a request into the context window
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextReport {
+    pub strategy: ContextStrategy,
+    /// Context length of the model; `None` when unknown and nothing was dropped
+    pub context_length: Option<u32>,
+    /// Estimated prompt tokens before planning
+    pub original_tokens: u32,
+    /// Estimated prompt tokens sent to the model
+    pub estimated_tokens: u32,
+    pub dropped_message_ids: Vec<String>,
+    /// Summary that replaced the dropped messages, under the summarise strategy
+    pub summary: Option<String>,
+    /// Why the dropped messages could not be summarised; they are then cut
+    /// from the history without a summary, or with an older one
+    pub summary_error: Option<String>,
+}
+
+/// Estimate the token count of a piece of text
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+pub fn estimate_message_tokens(message: &Message) -> u32 {
+    let attachments: u32 = message
+        .attachments
+        .iter()
+        .map(|attachment| {
+            if attachment.mime_type.starts_with("image/") {
+                IMAGE_TOKENS
+            } else {
+                (attachment.data.len() / CHARS_PER_TOKEN) as u32
+            }
+        })
+        .sum();
+    let tool_calls: u32 = message
+        .tool_calls
+        .iter()
+        .map(|call| estimate_tokens(&call.tool_name) + estimate_tokens(&call.arguments.to_string()))
+        .sum();
+
+    MESSAGE_OVERHEAD_TOKENS + estimate_tokens(&message.content) + attachments + tool_calls
+}
+
+/// Split history into turns that are kept or dropped as a whole; tool results
+/// stay with the assistant message that requested them
+pub fn group_turns(messages: &[Message]) -> Vec<std::ops::Range<usize>> {
+    let mut turns: Vec<std::ops::Range<usize>> = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        match turns.last_mut() {
+            Some(turn) if message.role == MessageRole::Tool => turn.end = index + 1,
+            _ => turns.push(index..index + 1),
+        }
+    }
+    turns
+}
+
+/// Build the pinned message that stands in for summarised turns
+pub fn summary_message(summary: &str) -> Message {
+    Message::new(
+        MessageRole::System,
+        format!("Summary of the earlier conversation:\n{summary}"),
+    )
+}
+
+/// Summary the summarise strategy wrote for a conversation's dropped turns
+struct DroppedSummary {
+    /// Newest message the summary covers
+    through_message_id: String,
+    content: String,
+}
+
+/// Looks up context lengths per model and trims requests to fit them
+pub struct ContextPlanner {
+    /// Context lengths of cloud models keyed by connection id, then model id
+    cloud_models: Mutex<HashMap<String, HashMap<String, u32>>>,
+    /// Lengths reported by backends, keyed by connection id and model
+    reported: Mutex<HashMap<(String, String), u32>>,
+    /// Summaries of dropped turns keyed by conversation id, so later requests
+    /// only summarise the turns dropped since
+    summaries: Mutex<HashMap<String, DroppedSummary>>,
+}
+
+impl ContextPlanner {
+    pub fn new() -> Self {
+        Self {
+            cloud_models: Mutex::new(HashMap::new()),
+            reported: Mutex::new(HashMap::new()),
+            summaries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the model context lengths of a cloud provider connection
+    pub fn set_cloud_models(
+        &self,
+        connection_id: &str,
+        models: &[CloudModel],
+    ) -> Result<(), String> {
+        let lengths = models
+            .iter()
+            .filter(|model| model.max_context > 0)
+            .map(|model| (model.id.clone(), model.max_context))
+            .collect();
+
+        let mut cloud_models = self.cloud_models.lock().map_err(|e| e.to_string())?;
+        cloud_models.insert(connection_id.to_string(), lengths);
+        Ok(())
+    }
+
+    /// Forget everything known about a connection's models, e.g. after it is
+    /// replaced or removed
+    pub fn forget_connection(&self, connection_id: &str) -> Result<(), String> {
+        self.cloud_models
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(connection_id);
+        self.reported
+            .lock()
+            .map_err(|e| e.to_string())?
+            .retain(|(id, _), _| id != connection_id);
+        Ok(())
+    }
+
+    /// Context length of a model: configured cloud limits first, then the
+    /// backend's own report, which is cached per connection
+    pub async fn context_length(
+        &self,
+        backend: &dyn ChatBackend,
+        connection_id: &str,
+        model: &str,
+    ) -> Result<Option<u32>, String> {
+        let configured = self
+            .cloud_models
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(connection_id)
+            .and_then(|models| models.get(model))
+            .copied();
+        if configured.is_some() {
+            return Ok(configured);
+        }
+
+        let key = (connection_id.to_string(), model.to_string());
+        if let Some(length) = self.reported.lock().map_err(|e| e.to_string())?.get(&key) {
+            return Ok(Some(*length));
+        }
+
+        // Failed or empty lookups are not cached so a later request can retry
+        let length = backend.context_length(model).await.unwrap_or(None);
+        if let Some(length) = length {
+            self.reported
+                .lock()
+                .map_err(|e| e.to_string())?
+                .insert(key, length);
+        }
+        Ok(length)
+    }
+
+    /// Drop or summarise older turns of `request` so it fits the model's context.
+    ///
+    /// The system prompt, pinned system messages and the latest turn are always
+    /// kept. Requests continuing a backend-side session only send the newest
+    /// turn, so they are left alone. Summaries are cached per conversation when
+    /// `conversation_id` is given.
+    pub async fn plan(
+        &self,
+        backend: &dyn ChatBackend,
+        connection_id: &str,
+        conversation_id: Option<&str>,
+        request: &mut BackendChatRequest,
+        strategy: ContextStrategy,
+    ) -> Result<ContextReport, String> {
+        let original_tokens = estimate_request_tokens(request);
+        let mut report = ContextReport {
+            strategy,
+            context_length: None,
+            original_tokens,
+            estimated_tokens: original_tokens,
+            dropped_message_ids: Vec::new(),
+            summary: None,
+            summary_error: None,
+        };
+
+        if request.session_id.is_some() && backend.capabilities().sessions {
+            return Ok(report);
+        }
+        // Local servers run the model with the requested window, whatever the
+        // model supports
+        let requested = request
+            .parameters
+            .num_ctx
+            .filter(|_| matches!(backend.kind(), BackendKind::Ollama | BackendKind::LmStudio));
+        let context_length = match requested {
+            Some(num_ctx) => Some(num_ctx),
+            None => {
+                self.context_length(backend, connection_id, &request.model)
+                    .await?
+            }
+        };
+        let Some(context_length) = context_length else {
+            return Ok(report);
+        };
+        report.context_length = Some(context_length);
+
+        let response_tokens = request
+            .parameters
+            .max_tokens
+            .unwrap_or_else(|| DEFAULT_RESPONSE_TOKENS.min(context_length / 4));
+        let budget = context_length.saturating_sub(response_tokens);
+        if original_tokens <= budget {
+            return Ok(report);
+        }
+
+        let turns = group_turns(&request.messages);
+        let turn_tokens: Vec<u32> = turns
+            .iter()
+            .map(|turn| {
+                request.messages[turn.clone()]
+                    .iter()
+                    .map(estimate_message_tokens)
+                    .sum()
+            })
+            .collect();
+        let pinned = |turn: &std::ops::Range<usize>| {
+            request.messages[turn.start].role == MessageRole::System
+        };
+
+        // Fixed cost: system prompt, tools, pinned messages and the latest turn
+        let last = turns.len().saturating_sub(1);
+        let mut keep = vec![false; turns.len()];
+        let mut used = original_tokens - turn_tokens.iter().sum::<u32>();
+        for (index, turn) in turns.iter().enumerate() {
+            if index == last || pinned(turn) {
+                keep[index] = true;
+                used += turn_tokens[index];
+            }
+        }
+        if strategy == ContextStrategy::Summarize {
+            used += SUMMARY_MAX_TOKENS;
+        }
+
+        let mut try_keep = |index: usize, used: &mut u32| {
+            if !keep[index] && *used + turn_tokens[index] <= budget {
+                keep[index] = true;
+                *used += turn_tokens[index];
+                true
+            } else {
+                keep[index]
+            }
+        };
+
+        if strategy == ContextStrategy::KeepFirstAndLast {
+            if let Some(first) = turns.iter().position(|turn| !pinned(turn)) {
+                try_keep(first, &mut used);
+                // Keep the reply to the opening message with it
+                if first + 1 < turns.len()
+                    && request.messages[turns[first + 1].start].role == MessageRole::Assistant
+                {
+                    try_keep(first + 1, &mut used);
+                }
+            }
+        }
+        // Newest turns first, stopping at the first turn that no longer fits
+        for index in (0..turns.len()).rev() {
+            if !try_keep(index, &mut used) {
+                break;
+            }
+        }
+
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+        for (index, turn) in turns.iter().enumerate() {
+            let target = if keep[index] { &mut kept } else { &mut dropped };
+            target.extend(request.messages[turn.clone()].iter().cloned());
+        }
+        if dropped.is_empty() {
+            return Ok(report);
+        }
+
+        report.dropped_message_ids = dropped.iter().map(|message| message.id.clone()).collect();
+        if strategy == ContextStrategy::Summarize {
+            let (summary, summary_error) = self
+                .summarize_dropped(
+                    backend,
+                    &request.model,
+                    conversation_id,
+                    &dropped,
+                    context_length,
+                )
+                .await?;
+            if let Some(summary) = summary {
+                let insert_at = kept
+                    .iter()
+                    .position(|message| message.role != MessageRole::System)
+                    .unwrap_or(kept.len());
+                kept.insert(insert_at, summary_message(&summary));
+                report.summary = Some(summary);
+            }
+            report.summary_error = summary_error;
+        }
+
+        request.messages = kept;
+        report.estimated_tokens = estimate_request_tokens(request);
+        Ok(report)
+    }
+
+    /// Summary of the dropped turns, extending the conversation's cached
+    /// summary with the turns dropped since it was written.
+    ///
+    /// Dropped turns only grow from the start of the history as it gets longer,
+    /// so the cached summary stays valid while its newest message is still
+    /// dropped. Returns the summary to send, and the error when the newly
+    /// dropped turns could not be summarised.
+    async fn summarize_dropped(
+        &self,
+        backend: &dyn ChatBackend,
+        model: &str,
+        conversation_id: Option<&str>,
+        dropped: &[Message],
+        context_length: u32,
+    ) -> Result<(Option<String>, Option<String>), String> {
+        let cached = match conversation_id {
+            Some(id) => self
+                .summaries
+                .lock()
+                .map_err(|e| e.to_string())?
+                .get(id)
+                .and_then(|cached| {
+                    dropped
+                        .iter()
+                        .position(|message| message.id == cached.through_message_id)
+                        .map(|index| (index + 1, cached.content.clone()))
+                }),
+            None => None,
+        };
+        let (covered, previous) = match cached {
+            Some((covered, content)) => (covered, Some(content)),
+            None => (0, None),
+        };
+
+        let new = &dropped[covered..];
+        if new.is_empty() {
+            return Ok((previous, None));
+        }
+        // Leave room for the instructions and the summary itself; without a
+        // summary the request still fits as a sliding window
+        let input = summary_input(new, context_length.saturating_sub(2 * SUMMARY_MAX_TOKENS));
+        if input.is_empty() {
+            let error = "The dropped messages are too long to summarise".to_string();
+            return Ok((previous, Some(error)));
+        }
+        let summary = match backend.summarize(model, previous.as_deref(), input).await {
+            Ok(summary) => summary,
+            Err(error) => return Ok((previous, Some(error))),
+        };
+
+        if let (Some(id), Some(last)) = (conversation_id, dropped.last()) {
+            self.summaries.lock().map_err(|e| e.to_string())?.insert(
+                id.to_string(),
+                DroppedSummary {
+                    through_message_id: last.id.clone(),
+                    content: summary.clone(),
+                },
+            );
+        }
+        Ok((Some(summary), None))
+    }
+}
+
+/// The newest dropped messages that fit in one summary request
+fn summary_input(dropped: &[Message], budget: u32) -> &[Message] {
+    let mut used = 0;
+    let start = dropped
+        .iter()
+        .rposition(|message| {
+            used += estimate_message_tokens(message);
+            used > budget
+        })
+        .map_or(0, |index| index + 1);
+    &dropped[start..]
+}
+
+/// Estimate the prompt tokens of a full request, including tool definitions
+pub fn estimate_request_tokens(request: &BackendChatRequest) -> u32 {
+    let system = request
+        .system_prompt
+        .as_deref()
+        .map(|prompt| MESSAGE_OVERHEAD_TOKENS + estimate_tokens(prompt))
+        .unwrap_or(0);
+    let tools: u32 = request
+        .tools
+        .iter()
+        .map(|tool| {
+            estimate_tokens(&tool.name)
+                + estimate_tokens(&tool.description)
+                + estimate_tokens(&tool.parameters.to_string())
+        })
+        .sum();
+    let messages: u32 = request.messages.iter().map(estimate_message_tokens).sum();
+
+    system + tools + messages
+}
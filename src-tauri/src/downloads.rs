@@ -0,0 +1,147 @@
+use crate::backend::PullProgress;
+use futures::future::{AbortHandle, AbortRegistration};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Number of model pulls allowed to download at the same time; the rest wait in the queue
+const MAX_CONCURRENT_PULLS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullState {
+    Queued,
+    Downloading,
+}
+
+/// Snapshot of a queued or running model pull
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullInfo {
+    pub id: String,
+    pub model_name: String,
+    pub connection_id: String,
+    pub state: PullState,
+    pub progress: Option<PullProgress>,
+}
+
+/// Payload of the `model_pull_progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullProgressEvent<'a> {
+    pub pull_id: &'a str,
+    pub model_name: &'a str,
+    pub connection_id: &'a str,
+    #[serde(flatten)]
+    pub progress: PullProgress,
+    pub done: bool,
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+}
+
+struct PullEntry {
+    info: PullInfo,
+    abort_handle: AbortHandle,
+}
+
+/// Tracks model pulls across connections and limits how many download at once
+pub struct DownloadManager {
+    slots: Semaphore,
+    pulls: Mutex<HashMap<String, PullEntry>>,
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        Self {
+            slots: Semaphore::new(MAX_CONCURRENT_PULLS),
+            pulls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register a pull in the queue, returning its id and abort registration.
+    ///
+    /// Fails if the same model is already being pulled on that connection.
+    pub fn enqueue(
+        &self,
+        model_name: &str,
+        connection_id: &str,
+    ) -> Result<(String, AbortRegistration), String> {
+        let mut pulls = self.pulls.lock().map_err(|e| e.to_string())?;
+        if pulls.values().any(|entry| {
+            entry.info.model_name == model_name && entry.info.connection_id == connection_id
+        }) {
+            return Err(format!("{} is already being pulled", model_name));
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        pulls.insert(
+            id.clone(),
+            PullEntry {
+                info: PullInfo {
+                    id: id.clone(),
+                    model_name: model_name.to_string(),
+                    connection_id: connection_id.to_string(),
+                    state: PullState::Queued,
+                    progress: None,
+                },
+                abort_handle,
+            },
+        );
+
+        Ok((id, abort_registration))
+    }
+
+    /// Wait for a free download slot
+    pub async fn acquire_slot(&self) -> Result<SemaphorePermit<'_>, String> {
+        self.slots.acquire().await.map_err(|e| e.to_string())
+    }
+
+    pub fn set_state(&self, id: &str, state: PullState) {
+        if let Ok(mut pulls) = self.pulls.lock() {
+            if let Some(entry) = pulls.get_mut(id) {
+                entry.info.state = state;
+            }
+        }
+    }
+
+    pub fn update_progress(&self, id: &str, progress: &PullProgress) {
+        if let Ok(mut pulls) = self.pulls.lock() {
+            if let Some(entry) = pulls.get_mut(id) {
+                entry.info.progress = Some(progress.clone());
+            }
+        }
+    }
+
+    /// Remove a finished, failed or cancelled pull
+    pub fn finish(&self, id: &str) {
+        if let Ok(mut pulls) = self.pulls.lock() {
+            pulls.remove(id);
+        }
+    }
+
+    /// Cancel a pull by id, or by model name when no pull has that id
+    pub fn cancel(&self, id_or_model: &str) -> Result<bool, String> {
+        let pulls = self.pulls.lock().map_err(|e| e.to_string())?;
+        let entry = pulls.get(id_or_model).or_else(|| {
+            pulls
+                .values()
+                .find(|entry| entry.info.model_name == id_or_model)
+        });
+
+        match entry {
+            Some(entry) => {
+                entry.abort_handle.abort();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<PullInfo>, String> {
+        let pulls = self.pulls.lock().map_err(|e| e.to_string())?;
+        Ok(pulls.values().map(|entry| entry.info.clone()).collect())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::{ConversationSummary, SummarizationConfig};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -45,6 +46,20 @@ pub struct PortableConversation {
     pub parameters: PortableModelParameters,
     pub project_id: Option<String>,
     pub tool_calling_enabled: bool,
+    #[serde(default)]
+    pub enabled_tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub context_strategy: Option<String>,
+    #[serde(default)]
+    pub think: Option<bool>,
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    #[serde(default)]
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    pub summarization: Option<SummarizationConfig>,
+    #[serde(default)]
+    pub summary: Option<ConversationSummary>,
     pub restored_from_folder: Option<bool>,
 }
 
@@ -59,6 +74,10 @@ pub struct PortableMessage {
     pub backend_type: Option<String>,
     pub is_error: bool,
     pub token_count: Option<u32>,
+    #[serde(default)]
+    pub prompt_token_count: Option<u32>,
+    #[serde(default)]
+    pub cost: Option<f64>,
     pub attachments: Vec<PortableAttachment>,
     pub tool_calls: Vec<PortableToolCall>,
     pub status: String,
@@ -103,6 +122,20 @@ pub struct PortableModelParameters {
     pub top_k: Option<u32>,
     pub top_p: Option<f64>,
     pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(default)]
+    pub repeat_penalty: Option<f64>,
+    #[serde(default)]
+    pub min_p: Option<f64>,
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    #[serde(default)]
+    pub mirostat: Option<u8>,
+    #[serde(default)]
+    pub num_gpu: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -151,6 +184,20 @@ struct ConversationMetaFile {
     parameters: PortableModelParameters,
     project_id: Option<String>,
     tool_calling_enabled: bool,
+    #[serde(default)]
+    enabled_tools: Option<Vec<String>>,
+    #[serde(default)]
+    context_strategy: Option<String>,
+    #[serde(default)]
+    think: Option<bool>,
+    #[serde(default)]
+    keep_alive: Option<String>,
+    #[serde(default)]
+    response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    summarization: Option<SummarizationConfig>,
+    #[serde(default)]
+    summary: Option<ConversationSummary>,
     message_count: usize,
 }
 
@@ -173,6 +220,10 @@ struct ConversationMessageFile {
     backend_type: Option<String>,
     is_error: bool,
     token_count: Option<u32>,
+    #[serde(default)]
+    prompt_token_count: Option<u32>,
+    #[serde(default)]
+    cost: Option<f64>,
     attachments: Vec<AttachmentReferenceFile>,
     tool_calls: Vec<PortableToolCall>,
     status: String,
@@ -280,6 +331,13 @@ pub fn save_folder_sync_snapshot(
                 parameters: conversation.parameters.clone(),
                 project_id: conversation.project_id.clone(),
                 tool_calling_enabled: conversation.tool_calling_enabled,
+                enabled_tools: conversation.enabled_tools.clone(),
+                context_strategy: conversation.context_strategy.clone(),
+                think: conversation.think,
+                keep_alive: conversation.keep_alive.clone(),
+                response_format: conversation.response_format.clone(),
+                summarization: conversation.summarization.clone(),
+                summary: conversation.summary.clone(),
                 message_count: conversation.messages.len(),
             },
         )?;
@@ -333,6 +391,8 @@ pub fn save_folder_sync_snapshot(
                     backend_type: message.backend_type.clone(),
                     is_error: message.is_error,
                     token_count: message.token_count,
+                    prompt_token_count: message.prompt_token_count,
+                    cost: message.cost,
                     attachments,
                     tool_calls: message.tool_calls.clone(),
                     status: message.status.clone(),
@@ -494,6 +554,8 @@ fn read_conversation_directories(
                     backend_type: message.backend_type.or_else(|| meta.backend_type.clone()),
                     is_error: message.is_error,
                     token_count: message.token_count,
+                    prompt_token_count: message.prompt_token_count,
+                    cost: message.cost,
                     attachments,
                     tool_calls: message.tool_calls,
                     status: message.status,
@@ -516,6 +578,13 @@ fn read_conversation_directories(
             parameters: meta.parameters,
             project_id: meta.project_id,
             tool_calling_enabled: meta.tool_calling_enabled,
+            enabled_tools: meta.enabled_tools,
+            context_strategy: meta.context_strategy,
+            think: meta.think,
+            keep_alive: meta.keep_alive,
+            response_format: meta.response_format,
+            summarization: meta.summarization,
+            summary: meta.summary,
             restored_from_folder: Some(true),
         });
     }
@@ -0,0 +1,695 @@
+use crate::backend::{
+    BackendCapabilities, BackendChatRequest, BackendChatResponse, BackendKind, ChatBackend,
+    ChunkCallback, StreamChunk,
+};
+use crate::models::{
+    Attachment, Message, MessageRole, OllamaModel, OllamaModelDetails, ToolCall, ToolCallStatus,
+};
+use async_trait::async_trait;
+use base64::Engine;
+use futures::StreamExt;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const ERROR_BODY_LIMIT: usize = 280;
+
+/// Finish reasons that mean the candidate was withheld rather than completed
+const BLOCKED_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "RECITATION",
+    "BLOCKLIST",
+    "PROHIBITED_CONTENT",
+    "SPII",
+    "IMAGE_SAFETY",
+];
+
+/// Client for the Google Gemini API
+#[derive(Clone)]
+pub struct GeminiClient {
+    client: Client,
+    /// API root including the version segment, e.g. `https://generativelanguage.googleapis.com/v1beta`
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelsResponse {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelEntry {
+    name: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    input_token_limit: Option<u32>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<CandidateContent>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    thought: bool,
+    #[serde(default)]
+    function_call: Option<FunctionCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    #[serde(default)]
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SafetyRating {
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    probability: Option<String>,
+    #[serde(default)]
+    blocked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: Option<u32>,
+    #[serde(default)]
+    candidates_token_count: Option<u32>,
+}
+
+/// A prompt or response withheld by Gemini's safety filters
+#[derive(Debug)]
+struct SafetyBlock {
+    /// `prompt` when the input was rejected, `response` when the output was withheld
+    stage: &'static str,
+    reason: String,
+    ratings: Vec<SafetyRating>,
+}
+
+impl SafetyBlock {
+    /// Error string in the form `Gemini blocked the response (SAFETY): harassment (HIGH)`
+    fn message(&self) -> String {
+        let flagged: Vec<&SafetyRating> = self
+            .ratings
+            .iter()
+            .filter(|rating| {
+                rating.blocked
+                    || matches!(rating.probability.as_deref(), Some("MEDIUM") | Some("HIGH"))
+            })
+            .collect();
+
+        let mut message = format!("Gemini blocked the {} ({})", self.stage, self.reason);
+        if !flagged.is_empty() {
+            let categories = flagged
+                .iter()
+                .map(|rating| {
+                    let category = rating
+                        .category
+                        .trim_start_matches("HARM_CATEGORY_")
+                        .replace('_', " ")
+                        .to_lowercase();
+                    match rating.probability.as_deref() {
+                        Some(probability) => format!("{category} ({probability})"),
+                        None => category,
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            message.push_str(": ");
+            message.push_str(&categories);
+        }
+
+        message
+    }
+}
+
+fn trim_error_body(body: &str) -> String {
+    let trimmed = body.trim();
+    let mut truncated = trimmed.chars().take(ERROR_BODY_LIMIT).collect::<String>();
+    if trimmed.chars().count() > ERROR_BODY_LIMIT {
+        truncated.push('…');
+    }
+    truncated
+}
+
+fn extract_error_message(payload: &Value) -> Option<String> {
+    payload
+        .pointer("/error/message")
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+}
+
+fn attachment_parts(attachments: &[Attachment]) -> Vec<Value> {
+    attachments
+        .iter()
+        .filter(|attachment| {
+            let mime_type = attachment.mime_type.as_str();
+            mime_type.starts_with("image/")
+                || mime_type.starts_with("text/")
+                || mime_type == "application/pdf"
+        })
+        .map(|attachment| {
+            json!({
+                "inlineData": {
+                    "mimeType": attachment.mime_type,
+                    "data": base64::engine::general_purpose::STANDARD.encode(&attachment.data),
+                },
+            })
+        })
+        .collect()
+}
+
+fn text_part(text: &str) -> Option<Value> {
+    (!text.trim().is_empty()).then(|| json!({ "text": text }))
+}
+
+/// Function responses must be JSON objects; plain text results are wrapped
+fn function_response(content: &str) -> Value {
+    match serde_json::from_str::<Value>(content) {
+        Ok(value @ Value::Object(_)) => value,
+        Ok(value) => json!({ "result": value }),
+        Err(_) => json!({ "result": content }),
+    }
+}
+
+/// Convert app messages to Gemini `contents`.
+///
+/// Assistant turns use the `model` role, system messages are folded into the
+/// system instruction and tool results become `functionResponse` parts in a
+/// user turn. Consecutive turns with the same role are merged.
+fn messages_to_gemini(
+    messages: &[Message],
+    system_prompt: Option<&str>,
+) -> (Option<String>, Vec<Value>) {
+    let mut system_parts: Vec<&str> = system_prompt
+        .map(str::trim)
+        .filter(|prompt| !prompt.is_empty())
+        .into_iter()
+        .collect();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for message in messages {
+        let (role, parts) = match message.role {
+            MessageRole::System => {
+                if !message.content.trim().is_empty() {
+                    system_parts.push(message.content.trim());
+                }
+                continue;
+            }
+            MessageRole::User => {
+                let mut parts = attachment_parts(&message.attachments);
+                parts.extend(text_part(&message.content));
+                ("user", parts)
+            }
+            MessageRole::Assistant => {
+                let mut parts: Vec<Value> = text_part(&message.content).into_iter().collect();
+                parts.extend(message.tool_calls.iter().map(|call| {
+                    json!({
+                        "functionCall": {
+                            "name": call.tool_name,
+                            "args": call.arguments,
+                        },
+                    })
+                }));
+                ("model", parts)
+            }
+            MessageRole::Tool => {
+                let parts = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "functionResponse": {
+                                "name": call.tool_name,
+                                "response": function_response(&message.content),
+                            },
+                        })
+                    })
+                    .collect();
+                ("user", parts)
+            }
+        };
+
+        if parts.is_empty() {
+            continue;
+        }
+        match turns.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => turns.push((role, parts)),
+        }
+    }
+
+    let system = (!system_parts.is_empty()).then(|| system_parts.join("\n\n"));
+    let contents = turns
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect();
+
+    (system, contents)
+}
+
+fn to_tool_call(call: FunctionCall) -> ToolCall {
+    ToolCall {
+        id: call
+            .id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        tool_name: call.name,
+        arguments: if call.args.is_null() {
+            json!({})
+        } else {
+            call.args
+        },
+        status: ToolCallStatus::Pending,
+        result: None,
+        error_message: None,
+        execution_time_ms: None,
+    }
+}
+
+fn to_ollama_model(model: ModelEntry) -> OllamaModel {
+    OllamaModel {
+        name: model
+            .name
+            .strip_prefix("models/")
+            .unwrap_or(&model.name)
+            .to_string(),
+        modified_at: None,
+        size: None,
+        digest: None,
+        details: Some(OllamaModelDetails {
+            format: Some("gemini".to_string()),
+            family: Some("gemini".to_string()),
+            families: None,
+            parameter_size: None,
+            quantization_level: model.display_name,
+        }),
+        connection_id: None,
+    }
+}
+
+/// Return the safety block carried by a response, if any
+fn safety_block(response: &GenerateContentResponse) -> Option<SafetyBlock> {
+    if let Some(feedback) = &response.prompt_feedback {
+        if let Some(reason) = &feedback.block_reason {
+            return Some(SafetyBlock {
+                stage: "prompt",
+                reason: reason.clone(),
+                ratings: feedback.safety_ratings.clone(),
+            });
+        }
+    }
+
+    let candidate = response.candidates.first()?;
+    let reason = candidate.finish_reason.as_deref()?;
+    BLOCKED_FINISH_REASONS
+        .contains(&reason)
+        .then(|| SafetyBlock {
+            stage: "response",
+            reason: reason.to_string(),
+            ratings: candidate.safety_ratings.clone(),
+        })
+}
+
+/// Merge one response (or streamed chunk) into the result being assembled.
+///
+/// Text from thought parts is skipped; a safety block becomes an error.
+fn apply_response(
+    response: GenerateContentResponse,
+    result: &mut BackendChatResponse,
+    on_chunk: &mut ChunkCallback<'_>,
+) -> Result<(), String> {
+    if let Some(block) = safety_block(&response) {
+        return Err(block.message());
+    }
+
+    // Streamed chunks repeat the running totals, so the last one wins
+    if let Some(usage) = response.usage_metadata {
+        result.prompt_eval_count = usage.prompt_token_count.or(result.prompt_eval_count);
+        result.eval_count = usage.candidates_token_count.or(result.eval_count);
+    }
+
+    let parts = response
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|candidate| candidate.content)
+        .map(|content| content.parts)
+        .unwrap_or_default();
+
+    for part in parts {
+        if let Some(call) = part.function_call {
+            result.tool_calls.push(to_tool_call(call));
+            continue;
+        }
+        if part.thought {
+            continue;
+        }
+        if let Some(text) = part.text.filter(|text| !text.is_empty()) {
+            result.content.push_str(&text);
+            on_chunk(StreamChunk::Content(&text));
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply one `data:` line of the SSE stream to the response being assembled
+fn process_stream_line(
+    line: &str,
+    result: &mut BackendChatResponse,
+    on_chunk: &mut ChunkCallback<'_>,
+) -> Result<(), String> {
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Ok(());
+    };
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let payload: Value = serde_json::from_str(data)
+        .map_err(|error| format!("Failed to parse Gemini stream chunk: {}", error))?;
+    if let Some(message) = extract_error_message(&payload) {
+        return Err(message);
+    }
+    let chunk: GenerateContentResponse = serde_json::from_value(payload)
+        .map_err(|error| format!("Failed to parse Gemini stream chunk: {}", error))?;
+
+    apply_response(chunk, result, on_chunk)
+}
+
+impl GeminiClient {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty()),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let request = self.client.request(method, url);
+
+        if let Some(api_key) = &self.api_key {
+            request.header("x-goog-api-key", api_key)
+        } else {
+            request
+        }
+    }
+
+    fn transport_error(&self, action: &str, error: reqwest::Error) -> String {
+        if error.is_timeout() {
+            return format!("{action} timed out while contacting Gemini");
+        }
+        if error.is_connect() {
+            return format!("{action} could not connect to Gemini at {}", self.base_url);
+        }
+
+        format!("{action} failed: {error}")
+    }
+
+    fn status_error(&self, action: &str, status: StatusCode, body: &str) -> String {
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return "Gemini authentication failed. Check the configured API key.".to_string();
+        }
+
+        let detail = serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|payload| extract_error_message(&payload))
+            .unwrap_or_else(|| trim_error_body(body));
+        if detail.is_empty() {
+            return format!("{action} failed with status: {status}");
+        }
+
+        format!("{action} failed ({status}): {detail}")
+    }
+
+    fn generate_body(&self, request: &BackendChatRequest) -> Value {
+        let (system, contents) =
+            messages_to_gemini(&request.messages, request.system_prompt.as_deref());
+        let parameters = &request.parameters;
+
+        let mut generation_config = json!({
+            "temperature": parameters.temperature,
+        });
+        if let Some(top_p) = parameters.top_p {
+            generation_config["topP"] = json!(top_p);
+        }
+        if let Some(top_k) = parameters.top_k {
+            generation_config["topK"] = json!(top_k);
+        }
+        if let Some(max_tokens) = parameters.max_tokens {
+            generation_config["maxOutputTokens"] = json!(max_tokens);
+        }
+        if let Some(seed) = parameters.seed {
+            generation_config["seed"] = json!(seed);
+        }
+        if let Some(stop) = parameters.stop_sequences() {
+            generation_config["stopSequences"] = json!(stop);
+        }
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": generation_config,
+        });
+
+        if let Some(system) = system {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    })
+                })
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+
+        body
+    }
+
+    async fn send_generate(
+        &self,
+        action: &str,
+        path: &str,
+        body: &Value,
+    ) -> Result<reqwest::Response, String> {
+        let response = self
+            .request(Method::POST, path)
+            .json(body)
+            .send()
+            .await
+            .map_err(|error| self.transport_error(action, error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error(action, status, &body));
+        }
+
+        Ok(response)
+    }
+}
+
+/// Model names may be given with or without the `models/` prefix
+fn model_path(model: &str) -> String {
+    let model = model.trim();
+    if model.starts_with("models/") || model.starts_with("tunedModels/") {
+        model.to_string()
+    } else {
+        format!("models/{model}")
+    }
+}
+
+#[async_trait]
+impl ChatBackend for GeminiClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Gemini
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: true,
+            model_details: false,
+            model_management: false,
+            sessions: false,
+            tools: true,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        ChatBackend::list_models(self).await.map(|_| true)
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let response = self
+            .request(Method::GET, "/models?pageSize=1000")
+            .send()
+            .await
+            .map_err(|error| self.transport_error("Gemini model listing", error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error("Gemini model listing", status, &body));
+        }
+
+        let models: ModelsResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse Gemini model list: {}", error))?;
+
+        // Embedding and other non-chat models cannot generate content
+        Ok(models
+            .models
+            .into_iter()
+            .filter(|model| {
+                model
+                    .supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(to_ollama_model)
+            .collect())
+    }
+
+    async fn context_length(&self, model: &str) -> Result<Option<u32>, String> {
+        let response = self
+            .request(Method::GET, &format!("/{}", model_path(model)))
+            .send()
+            .await
+            .map_err(|error| self.transport_error("Gemini model lookup", error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error("Gemini model lookup", status, &body));
+        }
+
+        let model: ModelEntry = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse Gemini model: {}", error))?;
+
+        Ok(model.input_token_limit)
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let body = self.generate_body(request);
+        let path = format!("/{}:generateContent", model_path(&request.model));
+        let response = self
+            .send_generate("Gemini chat request", &path, &body)
+            .await?;
+
+        let payload: GenerateContentResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse Gemini response: {}", error))?;
+
+        let mut result = BackendChatResponse::default();
+        apply_response(payload, &mut result, &mut |_| {})?;
+
+        Ok(result)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let body = self.generate_body(request);
+        let path = format!(
+            "/{}:streamGenerateContent?alt=sse",
+            model_path(&request.model)
+        );
+        let response = self
+            .send_generate("Gemini streaming chat request", &path, &body)
+            .await?;
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut result = BackendChatResponse::default();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|error| format!("Gemini stream error: {}", error))?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(newline_pos) = buffer.find('\n') {
+                let line = buffer[..newline_pos].trim().to_string();
+                buffer = buffer[newline_pos + 1..].to_string();
+
+                process_stream_line(&line, &mut result, on_chunk)?;
+            }
+        }
+
+        process_stream_line(buffer.trim(), &mut result, on_chunk)?;
+
+        Ok(result)
+    }
+}
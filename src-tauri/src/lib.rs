@@ -1,10 +1,22 @@
+mod anthropic;
+mod backend;
 mod commands;
+mod connections;
+mod context;
+mod downloads;
 mod folder_sync;
+mod gemini;
 mod lm_studio;
 mod models;
 mod ollama;
+mod openai_compat;
 mod opencode;
+mod secrets;
+mod structured;
+mod summary;
 mod sync_server;
+mod tools;
+mod usage;
 
 use commands::*;
 
@@ -20,20 +32,58 @@ pub fn run() {
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_shell::init())
         .manage(AppState::new())
+        .setup(|app| {
+            load_connections(app.handle())?;
+            load_cloud_providers(app.handle())?;
+            watch_running_models(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Connection
             test_connection,
             get_connection_status,
+            get_backend_capabilities,
+            save_connection,
+            save_cloud_provider,
+            list_cloud_providers,
+            remove_connection,
+            list_connections,
+            set_default_connection,
             // Models
             list_models,
             show_model,
             pull_model,
+            cancel_pull,
+            list_pulls,
             delete_model,
+            create_model,
+            copy_model,
+            rename_model,
+            export_modelfile,
+            list_running_models,
+            preload_model,
+            unload_model,
             // Chat
             send_message,
+            cancel_message,
             generate_title,
+            // Sessions
+            list_sessions,
+            get_session_messages,
+            abort_session,
+            delete_session,
+            share_session,
+            unshare_session,
+            summarize_session,
+            respond_opencode_permission,
             // Tools
+            list_tools,
+            update_tool_config,
             fetch_webpage,
+            web_search,
+            // Usage
+            get_usage_report,
+            set_usage_budget,
             // LAN Sync
             start_sync_server,
             stop_sync_server,
@@ -1,4 +1,12 @@
-use crate::models::Attachment;
+use crate::backend::{
+    latest_user_attachments, latest_user_text, BackendCapabilities, BackendChatRequest,
+    BackendChatResponse, BackendKind, ChatBackend, ChunkCallback, StreamChunk,
+};
+use crate::models::{
+    Attachment, Message, MessageRole, ModelParameters, OllamaModel, OllamaModelDetails,
+};
+use crate::structured::ResponseFormat;
+use async_trait::async_trait;
 use base64::Engine;
 use futures::StreamExt;
 use reqwest::{Client, Method, RequestBuilder, StatusCode};
@@ -40,7 +48,10 @@ pub struct LmStudioQuantization {
 #[derive(Debug, Clone)]
 pub struct LmStudioChatResult {
     pub content: String,
+    pub reasoning: String,
     pub response_id: Option<String>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
 }
 
 #[derive(Serialize)]
@@ -55,6 +66,19 @@ struct ChatRequest<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f64>,
+    /// Context length to load the model with when it is not loaded yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context_length: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     previous_response_id: Option<&'a str>,
 }
 
@@ -138,13 +162,41 @@ fn extract_chat_content(payload: &Value) -> String {
     combined
 }
 
+/// Text of the `reasoning` output items of a chat response
+fn extract_chat_reasoning(payload: &Value) -> String {
+    payload
+        .get("output")
+        .and_then(|value| value.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter(|item| {
+                    item.get("type").and_then(|value| value.as_str()) == Some("reasoning")
+                })
+                .filter_map(|item| item.get("content").and_then(|value| value.as_str()))
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
 fn parse_chat_result(payload: &Value) -> LmStudioChatResult {
+    let stat = |name: &str| {
+        payload
+            .get("stats")
+            .and_then(|stats| stats.get(name))
+            .and_then(|value| value.as_u64())
+            .map(|value| value as u32)
+    };
+
     LmStudioChatResult {
         content: extract_chat_content(payload),
+        reasoning: extract_chat_reasoning(payload),
         response_id: payload
             .get("response_id")
             .and_then(|value| value.as_str())
             .map(|value| value.to_string()),
+        input_tokens: stat("input_tokens"),
+        output_tokens: stat("total_output_tokens"),
     }
 }
 
@@ -174,6 +226,81 @@ fn build_input(prompt: &str, attachments: &[Attachment]) -> Value {
     }
 }
 
+fn non_empty(text: String) -> Option<String> {
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn strip_model_prefix(model: &str) -> &str {
+    model.strip_prefix("lmstudio:").unwrap_or(model)
+}
+
+fn build_prompt(messages: &[Message]) -> String {
+    let mut lines = Vec::new();
+
+    for message in messages {
+        let content = message.content.trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let role = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+            MessageRole::System => "System",
+            MessageRole::Tool => "Tool",
+        };
+
+        lines.push(format!("{role}: {content}"));
+    }
+
+    lines.join("\n")
+}
+
+/// Resolve the prompt text for a request, sending only the latest user turn
+/// when LM Studio already holds the earlier context via `previous_response_id`.
+fn resolve_prompt(request: &BackendChatRequest) -> Result<String, String> {
+    let prompt = if request.session_id.is_some() {
+        latest_user_text(&request.messages).unwrap_or_default()
+    } else {
+        build_prompt(&request.messages)
+    };
+
+    if prompt.trim().is_empty() {
+        return Err("No user message found to send".to_string());
+    }
+
+    Ok(prompt)
+}
+
+fn to_ollama_model(model: LmStudioModel) -> OllamaModel {
+    let model_key = model.key.clone();
+    let family = model.publisher.clone();
+    let display_name = model.display_name_or_key();
+    let parameter_size = model
+        .params_string
+        .clone()
+        .or_else(|| model.max_context_length.map(|value| format!("{value} ctx")));
+    let quantization_level = model
+        .quantization
+        .as_ref()
+        .and_then(|quantization| quantization.name.clone());
+
+    OllamaModel {
+        name: format!("lmstudio:{}", model_key),
+        modified_at: None,
+        size: Some(model.size_bytes),
+        digest: None,
+        details: Some(OllamaModelDetails {
+            format: Some("lmstudio".to_string()),
+            family: Some(family),
+            families: None,
+            parameter_size,
+            quantization_level: quantization_level.or(Some(display_name)),
+        }),
+        connection_id: None,
+    }
+}
+
 fn process_stream_event<F>(
     event_name: &str,
     data: &str,
@@ -181,7 +308,7 @@ fn process_stream_event<F>(
     on_chunk: &mut F,
 ) -> Result<(), String>
 where
-    F: FnMut(&str),
+    F: FnMut(StreamChunk<'_>),
 {
     if data.trim().is_empty() {
         return Ok(());
@@ -198,7 +325,17 @@ where
                 .filter(|content| !content.is_empty())
             {
                 final_result.content.push_str(content);
-                on_chunk(content);
+                on_chunk(StreamChunk::Content(content));
+            }
+        }
+        "reasoning.delta" => {
+            if let Some(content) = payload
+                .get("content")
+                .and_then(|value| value.as_str())
+                .filter(|content| !content.is_empty())
+            {
+                final_result.reasoning.push_str(content);
+                on_chunk(StreamChunk::Reasoning(content));
             }
         }
         "chat.end" => {
@@ -206,14 +343,22 @@ where
             let result = parse_chat_result(result_payload);
             let LmStudioChatResult {
                 content,
+                reasoning,
                 response_id,
+                input_tokens,
+                output_tokens,
             } = result;
             if final_result.response_id.is_none() {
                 final_result.response_id = response_id;
             }
+            final_result.input_tokens = input_tokens.or(final_result.input_tokens);
+            final_result.output_tokens = output_tokens.or(final_result.output_tokens);
             if final_result.content.is_empty() && !content.is_empty() {
                 final_result.content = content;
             }
+            if final_result.reasoning.is_empty() && !reasoning.is_empty() {
+                final_result.reasoning = reasoning;
+            }
         }
         "error" => {
             return Err(
@@ -350,8 +495,8 @@ impl LmStudioClient {
         prompt: &str,
         attachments: &[Attachment],
         system_prompt: Option<&str>,
-        temperature: f64,
-        max_tokens: Option<u32>,
+        parameters: &ModelParameters,
+        response_format: Option<&ResponseFormat>,
         previous_response_id: Option<&str>,
     ) -> Result<LmStudioChatResult, String> {
         let request = ChatRequest {
@@ -359,9 +504,15 @@ impl LmStudioClient {
             input: build_input(prompt, attachments),
             stream: false,
             store: false,
-            temperature: temperature.clamp(0.0, 2.0),
+            temperature: parameters.temperature.clamp(0.0, 2.0),
             system_prompt: system_prompt.filter(|prompt| !prompt.trim().is_empty()),
-            max_output_tokens: max_tokens,
+            max_output_tokens: parameters.max_tokens,
+            top_p: parameters.top_p,
+            top_k: parameters.top_k,
+            min_p: parameters.min_p,
+            repeat_penalty: parameters.repeat_penalty,
+            context_length: parameters.num_ctx,
+            response_format: response_format.map(|format| format.json_schema_format()),
             previous_response_id,
         };
 
@@ -392,22 +543,28 @@ impl LmStudioClient {
         prompt: &str,
         attachments: &[Attachment],
         system_prompt: Option<&str>,
-        temperature: f64,
-        max_tokens: Option<u32>,
+        parameters: &ModelParameters,
+        response_format: Option<&ResponseFormat>,
         previous_response_id: Option<&str>,
         mut on_chunk: F,
     ) -> Result<LmStudioChatResult, String>
     where
-        F: FnMut(&str),
+        F: FnMut(StreamChunk<'_>),
     {
         let request = ChatRequest {
             model: model_id,
             input: build_input(prompt, attachments),
             stream: true,
             store: false,
-            temperature: temperature.clamp(0.0, 2.0),
+            temperature: parameters.temperature.clamp(0.0, 2.0),
             system_prompt: system_prompt.filter(|prompt| !prompt.trim().is_empty()),
-            max_output_tokens: max_tokens,
+            max_output_tokens: parameters.max_tokens,
+            top_p: parameters.top_p,
+            top_k: parameters.top_k,
+            min_p: parameters.min_p,
+            repeat_penalty: parameters.repeat_penalty,
+            context_length: parameters.num_ctx,
+            response_format: response_format.map(|format| format.json_schema_format()),
             previous_response_id,
         };
 
@@ -436,7 +593,10 @@ impl LmStudioClient {
         let mut data_buffer = String::new();
         let mut final_result = LmStudioChatResult {
             content: String::new(),
+            reasoning: String::new(),
             response_id: None,
+            input_tokens: None,
+            output_tokens: None,
         };
 
         while let Some(chunk) = stream.next().await {
@@ -486,3 +646,113 @@ impl LmStudioClient {
         Ok(final_result)
     }
 }
+
+#[async_trait]
+impl ChatBackend for LmStudioClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::LmStudio
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: true,
+            model_details: false,
+            model_management: false,
+            sessions: true,
+            tools: false,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        LmStudioClient::health_check(self).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let models = LmStudioClient::list_models(self).await?;
+        Ok(models.into_iter().map(to_ollama_model).collect())
+    }
+
+    async fn context_length(&self, model: &str) -> Result<Option<u32>, String> {
+        let key = strip_model_prefix(model);
+        let models = LmStudioClient::list_models(self).await?;
+        Ok(models
+            .into_iter()
+            .find(|candidate| candidate.key == key)
+            .and_then(|candidate| candidate.max_context_length))
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let prompt = resolve_prompt(request)?;
+        let attachments = latest_user_attachments(&request.messages);
+
+        let response = LmStudioClient::chat(
+            self,
+            strip_model_prefix(&request.model),
+            &prompt,
+            &attachments,
+            request.system_prompt.as_deref(),
+            &request.parameters,
+            request.response_format.as_ref(),
+            request.session_id.as_deref(),
+        )
+        .await?;
+
+        Ok(BackendChatResponse {
+            content: response.content,
+            reasoning: non_empty(response.reasoning),
+            session_id: response.response_id,
+            prompt_eval_count: response.input_tokens,
+            eval_count: response.output_tokens,
+            ..BackendChatResponse::default()
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let prompt = resolve_prompt(request)?;
+        let attachments = latest_user_attachments(&request.messages);
+        let mut full_content = String::new();
+        let mut full_reasoning = String::new();
+
+        let response = self
+            .chat_stream_with_callback(
+                strip_model_prefix(&request.model),
+                &prompt,
+                &attachments,
+                request.system_prompt.as_deref(),
+                &request.parameters,
+                request.response_format.as_ref(),
+                request.session_id.as_deref(),
+                |chunk| {
+                    match chunk {
+                        StreamChunk::Content(text) => full_content.push_str(text),
+                        StreamChunk::Reasoning(text) => full_reasoning.push_str(text),
+                        StreamChunk::ToolCall(_) | StreamChunk::PermissionRequest(_) => {}
+                    }
+                    on_chunk(chunk);
+                },
+            )
+            .await?;
+
+        Ok(BackendChatResponse {
+            content: if full_content.is_empty() {
+                response.content
+            } else {
+                full_content
+            },
+            reasoning: non_empty(if full_reasoning.is_empty() {
+                response.reasoning
+            } else {
+                full_reasoning
+            }),
+            session_id: response.response_id,
+            prompt_eval_count: response.input_tokens,
+            eval_count: response.output_tokens,
+            ..BackendChatResponse::default()
+        })
+    }
+}
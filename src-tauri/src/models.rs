@@ -1,21 +1,49 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
-/// Ollama connection configuration
+/// Backend connection configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Connection {
     pub id: String,
     pub name: String,
+    #[serde(default = "default_connection_backend")]
+    pub backend: String,
     pub host: String,
     pub port: u16,
     pub use_https: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub api_token: Option<String>,
+    /// Full API base URL for cloud providers, used instead of host and port
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    #[serde(default)]
     pub is_default: bool,
+    #[serde(default = "Utc::now")]
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
     pub last_connected_at: Option<DateTime<Utc>>,
 }
 
+fn default_connection_backend() -> String {
+    "ollama".to_string()
+}
+
 impl Connection {
     pub fn base_url(&self) -> String {
+        if let Some(api_base_url) = self
+            .api_base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+        {
+            return api_base_url.trim_end_matches('/').to_string();
+        }
+
         let scheme = if self.use_https { "https" } else { "http" };
         format!("{}://{}:{}", scheme, self.host, self.port)
     }
@@ -26,9 +54,14 @@ impl Default for Connection {
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             name: "Local Ollama".to_string(),
+            backend: default_connection_backend(),
             host: "localhost".to_string(),
             port: 11434,
             use_https: false,
+            username: None,
+            password: None,
+            api_token: None,
+            api_base_url: None,
             is_default: true,
             created_at: Utc::now(),
             last_connected_at: None,
@@ -46,10 +79,42 @@ pub struct Message {
     pub model_name: Option<String>,
     pub is_error: bool,
     pub token_count: Option<u32>,
+    /// Prompt tokens the backend processed to produce this message
+    #[serde(default)]
+    pub prompt_token_count: Option<u32>,
+    /// Cost in USD, when the model has a price or the backend reports one
+    #[serde(default)]
+    pub cost: Option<f64>,
     pub attachments: Vec<Attachment>,
     pub tool_calls: Vec<ToolCall>,
     pub status: MessageStatus,
     pub status_message: Option<String>,
+    /// Reasoning the model produced before answering
+    #[serde(default)]
+    pub reasoning: Option<String>,
+}
+
+impl Message {
+    /// A sent message with a fresh id and the current time, for requests the
+    /// app builds itself
+    pub fn new(role: MessageRole, content: impl Into<String>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            role,
+            content: content.into(),
+            timestamp: Utc::now(),
+            model_name: None,
+            is_error: false,
+            token_count: None,
+            prompt_token_count: None,
+            cost: None,
+            attachments: Vec::new(),
+            tool_calls: Vec::new(),
+            status: MessageStatus::Sent,
+            status_message: None,
+            reasoning: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -115,6 +180,17 @@ pub struct ToolResult {
     pub summary: Option<String>,
 }
 
+impl ToolResult {
+    pub fn failure(message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self {
+            success: false,
+            data: serde_json::json!({ "error": &message }),
+            summary: Some(message),
+        }
+    }
+}
+
 /// Conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
@@ -128,6 +204,81 @@ pub struct Conversation {
     pub parameters: ModelParameters,
     pub project_id: Option<String>,
     pub tool_calling_enabled: bool,
+    /// Tools enabled for this conversation; `None` enables every registered tool
+    #[serde(default)]
+    pub enabled_tools: Option<Vec<String>>,
+    /// How history is fitted into the model's context window
+    #[serde(default)]
+    pub context_strategy: ContextStrategy,
+    /// Ask thinking models to reason before answering; `None` keeps the
+    /// model's default
+    #[serde(default)]
+    pub think: Option<bool>,
+    /// How long Ollama keeps the model loaded after a request, e.g. `10m`,
+    /// `-1` to keep it loaded or `0` to unload it; `None` keeps the server default
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// `"json"` or a JSON Schema that responses must follow
+    #[serde(default)]
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    pub summarization: SummarizationConfig,
+    /// Rolling summary standing in for the oldest messages
+    #[serde(default)]
+    pub summary: Option<ConversationSummary>,
+}
+
+/// Opt-in rolling summarisation of long conversations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SummarizationConfig {
+    pub enabled: bool,
+    /// Summarise once the unsummarised history passes this many estimated tokens
+    pub token_threshold: u32,
+    /// Model that writes the summary; defaults to the conversation's model
+    pub model: Option<String>,
+    /// Connection serving `model`; defaults to the conversation's connection
+    pub connection_id: Option<String>,
+    /// Most recent messages that are never folded into the summary
+    pub keep_recent_messages: usize,
+}
+
+impl Default for SummarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token_threshold: 6000,
+            model: None,
+            connection_id: None,
+            keep_recent_messages: 6,
+        }
+    }
+}
+
+/// Summary of a conversation's oldest messages, updated as the chat grows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub content: String,
+    /// Newest message folded into the summary; it and every earlier message
+    /// are replaced by the summary in requests
+    pub through_message_id: String,
+    pub summarized_message_count: usize,
+    pub model: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How older messages are dropped when a conversation outgrows the context window
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Keep the most recent messages that fit
+    #[default]
+    SlidingWindow,
+    /// Keep the opening exchange plus the most recent messages that fit
+    KeepFirstAndLast,
+    /// Replace dropped messages with a model-written summary
+    Summarize,
 }
 
 /// Model parameters
@@ -137,6 +288,26 @@ pub struct ModelParameters {
     pub top_k: Option<u32>,
     pub top_p: Option<f64>,
     pub max_tokens: Option<u32>,
+    /// Context window to run the model with, for backends that load models
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    /// Fixed sampling seed for reproducible output
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(default)]
+    pub repeat_penalty: Option<f64>,
+    /// Minimum token probability relative to the most likely token
+    #[serde(default)]
+    pub min_p: Option<f64>,
+    /// Sequences that end the response when generated
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    /// Mirostat sampling: 0 disables it, 1 and 2 select the algorithm version
+    #[serde(default)]
+    pub mirostat: Option<u8>,
+    /// Layers to offload to the GPU
+    #[serde(default)]
+    pub num_gpu: Option<i32>,
 }
 
 impl Default for ModelParameters {
@@ -146,6 +317,13 @@ impl Default for ModelParameters {
             top_k: Some(40),
             top_p: Some(0.9),
             max_tokens: None,
+            num_ctx: None,
+            seed: None,
+            repeat_penalty: None,
+            min_p: None,
+            stop: None,
+            mirostat: None,
+            num_gpu: None,
         }
     }
 }
@@ -156,7 +334,9 @@ impl ModelParameters {
             temperature: 1.2,
             top_k: Some(80),
             top_p: Some(0.95),
-            max_tokens: None,
+            min_p: Some(0.05),
+            repeat_penalty: Some(1.05),
+            ..Self::default()
         }
     }
 
@@ -165,18 +345,37 @@ impl ModelParameters {
             temperature: 0.2,
             top_k: Some(10),
             top_p: Some(0.5),
-            max_tokens: None,
+            min_p: Some(0.1),
+            repeat_penalty: Some(1.1),
+            ..Self::default()
         }
     }
 
+    /// Code repeats identifiers on purpose, so repetition is not penalised,
+    /// and whole files need a larger window than the usual default
     pub fn code() -> Self {
         Self {
             temperature: 0.1,
             top_k: Some(5),
             top_p: Some(0.3),
-            max_tokens: None,
+            min_p: Some(0.05),
+            repeat_penalty: Some(1.0),
+            num_ctx: Some(8192),
+            ..Self::default()
         }
     }
+
+    /// Stop sequences, without empty entries
+    pub fn stop_sequences(&self) -> Option<Vec<String>> {
+        let stop: Vec<String> = self
+            .stop
+            .iter()
+            .flatten()
+            .filter(|sequence| !sequence.is_empty())
+            .cloned()
+            .collect();
+        (!stop.is_empty()).then_some(stop)
+    }
 }
 
 /// Project workspace
@@ -202,6 +401,9 @@ pub struct OllamaModel {
     pub size: Option<u64>,
     pub digest: Option<String>,
     pub details: Option<OllamaModelDetails>,
+    /// Registry connection the model was listed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -227,14 +429,62 @@ pub struct OllamaChatRequest {
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<OllamaTool>>,
+    /// Enable or disable thinking on models that support it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub think: Option<bool>,
+    /// Seconds as a number, or a duration string such as `5m`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<serde_json::Value>,
+    /// `"json"` or a JSON Schema the response must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OllamaChatMessage {
     pub role: String,
+    #[serde(default)]
     pub content: String,
+    /// Reasoning returned separately when thinking is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub images: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OllamaToolCall>>,
+    /// Name of the tool whose result a `tool` role message carries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+}
+
+/// Tool advertised to Ollama in a chat request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaTool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub function: OllamaToolFunction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaToolFunction {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+/// Tool call requested by an Ollama model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaToolCall {
+    pub function: OllamaToolCallFunction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaToolCallFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -247,6 +497,20 @@ pub struct OllamaOptions {
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirostat: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_gpu: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -263,8 +527,66 @@ pub struct OllamaChatResponse {
     pub eval_duration: Option<u64>,
 }
 
+/// Model to build from a base model, baking in a system prompt, template,
+/// parameters and adapter
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCreateRequest {
+    /// Name of the new model
+    pub name: String,
+    /// Base model to build on
+    pub from: String,
+    pub system: Option<String>,
+    /// Prompt template in the server's template syntax
+    pub template: Option<String>,
+    pub parameters: Option<ModelParameters>,
+    /// Path on this machine to a LoRA adapter for the base model
+    pub adapter: Option<String>,
+}
+
+/// Model currently loaded into memory, from Ollama's `/api/ps`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaRunningModel {
+    pub name: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Bytes of the model held in GPU memory
+    #[serde(default)]
+    pub size_vram: Option<u64>,
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub details: Option<OllamaModelDetails>,
+    /// When the model is unloaded unless another request keeps it alive
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Connection the model is loaded on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaPsResponse {
+    #[serde(default)]
+    pub models: Vec<OllamaRunningModel>,
+}
+
+/// Progress line from Ollama's streaming `/api/pull`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaPullProgress {
+    #[serde(default)]
+    pub status: String,
+    pub digest: Option<String>,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    pub error: Option<String>,
+}
+
 /// Cloud API provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CloudProvider {
     pub id: String,
     pub name: String,
@@ -283,7 +605,34 @@ pub enum CloudProviderType {
     Google,
 }
 
+impl CloudProvider {
+    /// Build the connection used to talk to this provider
+    pub fn to_connection(&self) -> Result<Connection, String> {
+        let (backend, default_base_url) = match self.provider_type {
+            CloudProviderType::OpenAI => ("openai", "https://api.openai.com/v1"),
+            CloudProviderType::Anthropic => ("anthropic", "https://api.anthropic.com/v1"),
+            CloudProviderType::Google => ("gemini", "https://generativelanguage.googleapis.com/v1beta"),
+        };
+
+        Ok(Connection {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            backend: backend.to_string(),
+            api_token: self.api_key.clone(),
+            api_base_url: Some(
+                self.base_url
+                    .clone()
+                    .filter(|url| !url.trim().is_empty())
+                    .unwrap_or_else(|| default_base_url.to_string()),
+            ),
+            is_default: false,
+            ..Connection::default()
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CloudModel {
     pub id: String,
     pub name: String,
@@ -296,6 +645,7 @@ pub struct CloudModel {
 
 /// Tool configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct ToolConfig {
     pub enabled: bool,
     pub web_search_enabled: bool,
@@ -303,6 +653,13 @@ pub struct ToolConfig {
     pub max_search_results: u32,
     pub cache_search_results: bool,
     pub max_tool_calls: u32,
+    pub search_provider: SearchProvider,
+    /// Base URL of the search endpoint; required for SearXNG, overrides Jina's
+    pub search_endpoint: Option<String>,
+    /// Domains web tools may fetch from; empty allows any public domain
+    pub allowed_domains: Vec<String>,
+    /// Domains web tools must never fetch from, including subdomains
+    pub blocked_domains: Vec<String>,
 }
 
 impl Default for ToolConfig {
@@ -314,10 +671,22 @@ impl Default for ToolConfig {
             max_search_results: 5,
             cache_search_results: true,
             max_tool_calls: 10,
+            search_provider: SearchProvider::Jina,
+            search_endpoint: None,
+            allowed_domains: Vec::new(),
+            blocked_domains: Vec::new(),
         }
     }
 }
 
+/// Search engine used by the `web_search` tool
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProvider {
+    Jina,
+    Searxng,
+}
+
 /// App settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
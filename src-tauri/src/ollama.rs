@@ -1,11 +1,22 @@
+use crate::backend::{
+    split_think_tags, title_prompt, BackendCapabilities, BackendChatRequest, BackendChatResponse,
+    BackendKind, ChatBackend, ChunkCallback, ProgressCallback, PullProgress, StreamChunk,
+    ThinkTagSplitter,
+};
 use crate::models::*;
+use async_trait::async_trait;
 use reqwest::Client;
 use futures::StreamExt;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
 
 #[derive(Clone)]
 pub struct OllamaClient {
     client: Client,
+    /// Client without a total request timeout, for long-running streamed downloads
+    download_client: Client,
     base_url: String,
 }
 
@@ -16,14 +27,15 @@ impl OllamaClient {
                 .timeout(std::time::Duration::from_secs(300))
                 .build()
                 .unwrap_or_default(),
+            download_client: Client::builder()
+                .connect_timeout(std::time::Duration::from_secs(30))
+                .read_timeout(std::time::Duration::from_secs(300))
+                .build()
+                .unwrap_or_default(),
             base_url: base_url.trim_end_matches('/').to_string(),
         }
     }
 
-    pub fn update_base_url(&mut self, base_url: &str) {
-        self.base_url = base_url.trim_end_matches('/').to_string();
-    }
-
     /// Check if the Ollama server is reachable
     pub async fn health_check(&self) -> Result<bool, String> {
         let url = format!("{}/api/tags", self.base_url);
@@ -136,11 +148,11 @@ impl OllamaClient {
             model: model.to_string(),
             messages: vec![OllamaChatMessage {
                 role: "user".to_string(),
-                content: format!(
-                    "Generate a very short title (3-6 words, no quotes) for a conversation that starts with: {}",
-                    &first_message[..first_message.len().min(200)]
-                ),
+                content: title_prompt(first_message),
+                thinking: None,
                 images: None,
+                tool_calls: None,
+                tool_name: None,
             }],
             stream: Some(false),
             options: Some(OllamaOptions {
@@ -148,19 +160,40 @@ impl OllamaClient {
                 top_k: None,
                 top_p: None,
                 num_predict: Some(20),
+                num_ctx: None,
+                seed: None,
+                repeat_penalty: None,
+                min_p: None,
+                stop: None,
+                mirostat: None,
+                num_gpu: None,
             }),
+            tools: None,
+            think: None,
+            keep_alive: None,
+            format: None,
         };
 
         let response = self.chat(&request).await?;
         let title = response.message
-            .map(|m| m.content.trim().trim_matches('"').to_string())
+            .map(|m| split_think_tags(&m.content).0.trim().trim_matches('"').to_string())
             .unwrap_or_else(|| "New Conversation".to_string());
 
         Ok(title)
     }
 
-    /// Pull (download) a model
-    pub async fn pull_model(&self, model_name: &str) -> Result<String, String> {
+    /// Pull (download) a model, invoking callback for each NDJSON progress line.
+    ///
+    /// Ollama keeps partially downloaded layers, so pulling a model again after
+    /// an interrupted or cancelled pull resumes where it left off.
+    pub async fn pull_model_with_callback<F>(
+        &self,
+        model_name: &str,
+        mut on_progress: F,
+    ) -> Result<String, String>
+    where
+        F: FnMut(&OllamaPullProgress),
+    {
         let url = format!("{}/api/pull", self.base_url);
 
         #[derive(Serialize)]
@@ -169,21 +202,153 @@ impl OllamaClient {
             stream: bool,
         }
 
-        let resp = self.client.post(&url)
+        let resp = self.download_client.post(&url)
             .json(&PullRequest {
                 name: model_name.to_string(),
-                stream: false,
+                stream: true,
             })
             .send()
             .await
             .map_err(|e| format!("Failed to pull model: {}", e))?;
 
+        let last_status = read_progress(resp, "Pull", &mut on_progress).await?;
+
+        if last_status != "success" {
+            return Err(format!(
+                "Pull of {} ended before completion (last status: {})",
+                model_name,
+                if last_status.is_empty() { "none" } else { &last_status }
+            ));
+        }
+
+        Ok(format!("Successfully pulled {}", model_name))
+    }
+
+    /// Create a model from a base model, invoking callback for each NDJSON
+    /// progress line. A local adapter file is uploaded first.
+    pub async fn create_model_with_callback<F>(
+        &self,
+        request: &ModelCreateRequest,
+        mut on_progress: F,
+    ) -> Result<String, String>
+    where
+        F: FnMut(&OllamaPullProgress),
+    {
+        let url = format!("{}/api/create", self.base_url);
+
+        #[derive(Serialize)]
+        struct CreateRequest<'a> {
+            model: &'a str,
+            from: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            system: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            template: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            parameters: Option<OllamaOptions>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            adapters: Option<HashMap<String, String>>,
+            stream: bool,
+        }
+
+        let adapters = match request.adapter.as_deref().filter(|path| !path.trim().is_empty()) {
+            Some(path) => {
+                let path = Path::new(path);
+                let file_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .ok_or_else(|| format!("Invalid adapter path: {}", path.display()))?;
+                on_progress(&OllamaPullProgress {
+                    status: "uploading adapter".to_string(),
+                    digest: None,
+                    total: None,
+                    completed: None,
+                    error: None,
+                });
+                let digest = self.upload_blob(path).await?;
+                Some(HashMap::from([(file_name, digest)]))
+            }
+            None => None,
+        };
+
+        let resp = self.download_client.post(&url)
+            .json(&CreateRequest {
+                model: &request.name,
+                from: &request.from,
+                system: non_empty(request.system.as_deref()),
+                template: non_empty(request.template.as_deref()),
+                parameters: request.parameters.as_ref().map(ollama_options),
+                adapters,
+                stream: true,
+            })
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create model: {}", e))?;
+
+        let last_status = read_progress(resp, "Create", &mut on_progress).await?;
+
+        if last_status != "success" {
+            return Err(format!(
+                "Creation of {} ended before completion (last status: {})",
+                request.name,
+                if last_status.is_empty() { "none" } else { &last_status }
+            ));
+        }
+
+        Ok(format!("Successfully created {}", request.name))
+    }
+
+    /// Upload a local file as a blob unless the server already has it,
+    /// returning its digest
+    async fn upload_blob(&self, path: &Path) -> Result<String, String> {
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&data));
+        let url = format!("{}/api/blobs/{}", self.base_url, digest);
+
+        let exists = self.client.head(&url).send().await
+            .is_ok_and(|resp| resp.status().is_success());
+        if exists {
+            return Ok(digest);
+        }
+
+        let resp = self.download_client.post(&url)
+            .body(data)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload {}: {}", path.display(), e))?;
+
         if !resp.status().is_success() {
             let error_text = resp.text().await.unwrap_or_default();
-            return Err(format!("Pull failed: {}", error_text));
+            return Err(format!("Upload failed: {}", error_text));
         }
 
-        Ok(format!("Successfully pulled {}", model_name))
+        Ok(digest)
+    }
+
+    /// Copy a model under a new name
+    pub async fn copy_model(&self, source: &str, destination: &str) -> Result<(), String> {
+        let url = format!("{}/api/copy", self.base_url);
+
+        #[derive(Serialize)]
+        struct CopyRequest<'a> {
+            source: &'a str,
+            destination: &'a str,
+        }
+
+        let resp = self.client.post(&url)
+            .json(&CopyRequest { source, destination })
+            .send()
+            .await
+            .map_err(|e| format!("Failed to copy model: {}", e))?;
+
+        if !resp.status().is_success() {
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(format!("Copy failed: {}", error_text));
+        }
+
+        Ok(())
     }
 
     /// Delete a model
@@ -211,6 +376,62 @@ impl OllamaClient {
         Ok(())
     }
 
+    /// List the models currently loaded into memory
+    pub async fn list_running_models(&self) -> Result<Vec<OllamaRunningModel>, String> {
+        let url = format!("{}/api/ps", self.base_url);
+        let resp = self.client.get(&url).send().await
+            .map_err(|e| format!("Failed to connect: {}", e))?;
+
+        if !resp.status().is_success() {
+            return Err(format!("Server returned status: {}", resp.status()));
+        }
+
+        let ps: OllamaPsResponse = resp.json().await
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        Ok(ps.models)
+    }
+
+    /// Load or unload a model: a generate request without a prompt only
+    /// applies `keep_alive`, and a `keep_alive` of 0 unloads the model
+    async fn set_keep_alive(
+        &self,
+        model: &str,
+        keep_alive: Option<serde_json::Value>,
+    ) -> Result<(), String> {
+        let url = format!("{}/api/generate", self.base_url);
+
+        #[derive(Serialize)]
+        struct KeepAliveRequest<'a> {
+            model: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            keep_alive: Option<serde_json::Value>,
+        }
+
+        let resp = self.client.post(&url)
+            .json(&KeepAliveRequest { model, keep_alive })
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+
+        if !resp.status().is_success() {
+            let error_text = resp.text().await.unwrap_or_default();
+            return Err(format!("Request for {} failed: {}", model, error_text));
+        }
+
+        Ok(())
+    }
+
+    /// Load a model into memory ahead of the first message
+    pub async fn preload_model(&self, model: &str, keep_alive: Option<&str>) -> Result<(), String> {
+        self.set_keep_alive(model, keep_alive.map(keep_alive_value)).await
+    }
+
+    /// Unload a model, freeing the memory it occupies
+    pub async fn unload_model(&self, model: &str) -> Result<(), String> {
+        self.set_keep_alive(model, Some(serde_json::Value::from(0))).await
+    }
+
     /// Get model information
     pub async fn show_model(&self, model_name: &str) -> Result<serde_json::Value, String> {
         let url = format!("{}/api/show", self.base_url);
@@ -238,6 +459,344 @@ impl OllamaClient {
     }
 }
 
+#[async_trait]
+impl ChatBackend for OllamaClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Ollama
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: true,
+            model_details: true,
+            model_management: true,
+            sessions: false,
+            tools: true,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        OllamaClient::health_check(self).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        OllamaClient::list_models(self).await
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let response = OllamaClient::chat(self, &build_chat_request(request)).await?;
+
+        let (content, reasoning, tool_calls) = match response.message {
+            Some(message) => {
+                // Models that ignore `think` still inline their reasoning in tags
+                let (content, inline_reasoning) = split_think_tags(&message.content);
+                let reasoning = message
+                    .thinking
+                    .filter(|thinking| !thinking.trim().is_empty())
+                    .or(inline_reasoning);
+                (content, reasoning, tool_calls_from_ollama(message.tool_calls))
+            }
+            None => (String::new(), None, Vec::new()),
+        };
+
+        Ok(BackendChatResponse {
+            content,
+            reasoning,
+            session_id: None,
+            prompt_eval_count: response.prompt_eval_count,
+            eval_count: response.eval_count,
+            total_duration: response.total_duration,
+            cost: None,
+            tool_calls,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let mut result = BackendChatResponse::default();
+        let mut content = String::new();
+        let mut reasoning = String::new();
+        let mut splitter = ThinkTagSplitter::default();
+        let mut emit = |chunk: StreamChunk<'_>| {
+            match chunk {
+                StreamChunk::Content(text) => content.push_str(text),
+                StreamChunk::Reasoning(text) => reasoning.push_str(text),
+                StreamChunk::ToolCall(_) | StreamChunk::PermissionRequest(_) => {}
+            }
+            on_chunk(chunk);
+        };
+
+        self.chat_stream_with_callback(&build_chat_request(request), |chunk| {
+            if let Some(msg) = &chunk.message {
+                if let Some(thinking) = msg.thinking.as_deref().filter(|t| !t.is_empty()) {
+                    emit(StreamChunk::Reasoning(thinking));
+                }
+                // Models that ignore `think` still inline their reasoning in tags
+                if !msg.content.is_empty() {
+                    splitter.push(&msg.content, &mut emit);
+                }
+                result
+                    .tool_calls
+                    .extend(tool_calls_from_ollama(msg.tool_calls.clone()));
+            }
+
+            if let Some(pc) = chunk.prompt_eval_count {
+                result.prompt_eval_count = Some(pc);
+            }
+            if let Some(ec) = chunk.eval_count {
+                result.eval_count = Some(ec);
+            }
+            if let Some(td) = chunk.total_duration {
+                result.total_duration = Some(td);
+            }
+        })
+        .await?;
+        splitter.finish(&mut emit);
+
+        result.content = content;
+        result.reasoning = Some(reasoning).filter(|reasoning| !reasoning.trim().is_empty());
+        Ok(result)
+    }
+
+    async fn generate_title(&self, model: &str, first_message: &str) -> Result<String, String> {
+        OllamaClient::generate_title(self, model, first_message).await
+    }
+
+    async fn show_model(&self, model_name: &str) -> Result<serde_json::Value, String> {
+        OllamaClient::show_model(self, model_name).await
+    }
+
+    async fn context_length(&self, model: &str) -> Result<Option<u32>, String> {
+        let info = OllamaClient::show_model(self, model).await?;
+        Ok(context_length_from_show(&info))
+    }
+
+    async fn pull_model(
+        &self,
+        model_name: &str,
+        on_progress: &mut ProgressCallback<'_>,
+    ) -> Result<String, String> {
+        self.pull_model_with_callback(model_name, |progress| {
+            on_progress(&PullProgress::new(
+                progress.status.clone(),
+                progress.digest.clone(),
+                progress.completed,
+                progress.total,
+            ));
+        })
+        .await
+    }
+
+    async fn delete_model(&self, model_name: &str) -> Result<(), String> {
+        OllamaClient::delete_model(self, model_name).await
+    }
+
+    async fn create_model(
+        &self,
+        request: &ModelCreateRequest,
+        on_progress: &mut ProgressCallback<'_>,
+    ) -> Result<String, String> {
+        self.create_model_with_callback(request, |progress| {
+            on_progress(&PullProgress::new(
+                progress.status.clone(),
+                progress.digest.clone(),
+                progress.completed,
+                progress.total,
+            ));
+        })
+        .await
+    }
+
+    async fn copy_model(&self, source: &str, destination: &str) -> Result<(), String> {
+        OllamaClient::copy_model(self, source, destination).await
+    }
+
+    async fn list_running_models(&self) -> Result<Vec<OllamaRunningModel>, String> {
+        OllamaClient::list_running_models(self).await
+    }
+
+    async fn preload_model(&self, model: &str, keep_alive: Option<&str>) -> Result<(), String> {
+        OllamaClient::preload_model(self, model, keep_alive).await
+    }
+
+    async fn unload_model(&self, model: &str) -> Result<(), String> {
+        OllamaClient::unload_model(self, model).await
+    }
+}
+
+fn non_empty(text: Option<&str>) -> Option<&str> {
+    text.filter(|text| !text.trim().is_empty())
+}
+
+/// Read a streamed NDJSON progress response from `/api/pull` or `/api/create`,
+/// returning the last status reported
+async fn read_progress<F>(
+    resp: reqwest::Response,
+    action: &str,
+    on_progress: &mut F,
+) -> Result<String, String>
+where
+    F: FnMut(&OllamaPullProgress),
+{
+    if !resp.status().is_success() {
+        let error_text = resp.text().await.unwrap_or_default();
+        return Err(format!("{} failed: {}", action, error_text));
+    }
+
+    let mut stream = resp.bytes_stream();
+    let mut buffer = String::new();
+    let mut last_status = String::new();
+
+    let mut handle_line = |line: &str| -> Result<(), String> {
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        if let Ok(progress) = serde_json::from_str::<OllamaPullProgress>(line) {
+            if let Some(error) = &progress.error {
+                return Err(format!("{} failed: {}", action, error));
+            }
+            last_status = progress.status.clone();
+            on_progress(&progress);
+        }
+        Ok(())
+    };
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Stream error: {}", e))?;
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(newline_pos) = buffer.find('\n') {
+            let line = buffer[..newline_pos].trim().to_string();
+            buffer = buffer[newline_pos + 1..].to_string();
+            handle_line(&line)?;
+        }
+    }
+
+    handle_line(buffer.trim())?;
+    Ok(last_status)
+}
+
+/// Ollama takes `keep_alive` as seconds or a duration string; plain numbers
+/// such as `-1` are sent as seconds because they are not valid durations
+fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    let keep_alive = keep_alive.trim();
+    match keep_alive.parse::<i64>() {
+        Ok(seconds) => serde_json::Value::from(seconds),
+        Err(_) => serde_json::Value::from(keep_alive),
+    }
+}
+
+/// Context length from an `/api/show` response.
+///
+/// A `num_ctx` set in the Modelfile limits the window Ollama actually uses,
+/// so it wins over the `<arch>.context_length` the model was trained with.
+fn context_length_from_show(info: &serde_json::Value) -> Option<u32> {
+    let trained = info
+        .get("model_info")
+        .and_then(|model_info| model_info.as_object())
+        .and_then(|model_info| {
+            model_info
+                .iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, value)| value.as_u64())
+        })
+        .map(|value| value.min(u32::MAX as u64) as u32);
+
+    let num_ctx = info
+        .get("parameters")
+        .and_then(|parameters| parameters.as_str())
+        .and_then(|parameters| {
+            parameters.lines().find_map(|line| {
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next()) {
+                    (Some("num_ctx"), Some(value)) => value.parse::<u32>().ok(),
+                    _ => None,
+                }
+            })
+        });
+
+    match (num_ctx, trained) {
+        (Some(num_ctx), Some(trained)) => Some(num_ctx.min(trained)),
+        (num_ctx, trained) => num_ctx.or(trained),
+    }
+}
+
+/// Ollama options for model parameters, used for chat requests and as the
+/// parameters baked into created models
+fn ollama_options(params: &ModelParameters) -> OllamaOptions {
+    OllamaOptions {
+        temperature: Some(params.temperature),
+        top_k: params.top_k,
+        top_p: params.top_p,
+        num_predict: params.max_tokens,
+        num_ctx: params.num_ctx,
+        seed: params.seed,
+        repeat_penalty: params.repeat_penalty,
+        min_p: params.min_p,
+        stop: params.stop_sequences(),
+        mirostat: params.mirostat,
+        num_gpu: params.num_gpu,
+    }
+}
+
+/// Build an Ollama chat request from a backend-agnostic request
+fn build_chat_request(request: &BackendChatRequest) -> OllamaChatRequest {
+    let params = &request.parameters;
+
+    OllamaChatRequest {
+        model: request.model.clone(),
+        messages: messages_to_ollama(&request.messages, request.system_prompt.as_deref()),
+        stream: Some(false),
+        options: Some(ollama_options(params)),
+        think: request.think,
+        keep_alive: request.keep_alive.as_deref().map(keep_alive_value),
+        format: request
+            .response_format
+            .as_ref()
+            .map(|format| format.ollama_format()),
+        tools: if request.tools.is_empty() {
+            None
+        } else {
+            Some(
+                request
+                    .tools
+                    .iter()
+                    .map(|tool| OllamaTool {
+                        tool_type: "function".to_string(),
+                        function: OllamaToolFunction {
+                            name: tool.name.clone(),
+                            description: tool.description.clone(),
+                            parameters: tool.parameters.clone(),
+                        },
+                    })
+                    .collect(),
+            )
+        },
+    }
+}
+
+/// Convert tool calls returned by Ollama into pending app tool calls
+fn tool_calls_from_ollama(tool_calls: Option<Vec<OllamaToolCall>>) -> Vec<ToolCall> {
+    tool_calls
+        .unwrap_or_default()
+        .into_iter()
+        .map(|call| ToolCall {
+            id: uuid::Uuid::new_v4().to_string(),
+            tool_name: call.function.name,
+            arguments: call.function.arguments,
+            status: ToolCallStatus::Pending,
+            result: None,
+            error_message: None,
+            execution_time_ms: None,
+        })
+        .collect()
+}
+
 /// Helper to convert app messages to Ollama format
 pub fn messages_to_ollama(messages: &[Message], system_prompt: Option<&str>) -> Vec<OllamaChatMessage> {
     let mut ollama_messages = Vec::new();
@@ -247,7 +806,10 @@ pub fn messages_to_ollama(messages: &[Message], system_prompt: Option<&str>) ->
             ollama_messages.push(OllamaChatMessage {
                 role: "system".to_string(),
                 content: prompt.to_string(),
+                thinking: None,
                 images: None,
+                tool_calls: None,
+                tool_name: None,
             });
         }
     }
@@ -269,10 +831,34 @@ pub fn messages_to_ollama(messages: &[Message], system_prompt: Option<&str>) ->
             })
             .collect();
 
+        // Assistant turns carry the calls they requested; tool turns carry
+        // the call whose result they hold
+        let (tool_calls, tool_name) = match msg.role {
+            MessageRole::Assistant if !msg.tool_calls.is_empty() => (
+                Some(
+                    msg.tool_calls
+                        .iter()
+                        .map(|call| OllamaToolCall {
+                            function: OllamaToolCallFunction {
+                                name: call.tool_name.clone(),
+                                arguments: call.arguments.clone(),
+                            },
+                        })
+                        .collect(),
+                ),
+                None,
+            ),
+            MessageRole::Tool => (None, msg.tool_calls.first().map(|call| call.tool_name.clone())),
+            _ => (None, None),
+        };
+
         ollama_messages.push(OllamaChatMessage {
             role: role.to_string(),
             content: msg.content.clone(),
+            thinking: None,
             images: if images.is_empty() { None } else { Some(images) },
+            tool_calls,
+            tool_name,
         });
     }
 
@@ -0,0 +1,561 @@
+use crate::backend::{
+    BackendCapabilities, BackendChatRequest, BackendChatResponse, BackendKind, ChatBackend,
+    ChunkCallback, StreamChunk,
+};
+use crate::models::{
+    Message, MessageRole, OllamaModel, OllamaModelDetails, ToolCall, ToolCallStatus,
+};
+use async_trait::async_trait;
+use base64::Engine;
+use futures::StreamExt;
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Client for any server exposing the OpenAI `/v1/chat/completions` API,
+/// such as OpenAI, OpenRouter, vLLM or the llama.cpp server
+#[derive(Clone)]
+pub struct OpenAiCompatClient {
+    client: Client,
+    /// API root including the version segment, e.g. `https://api.openai.com/v1`
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    owned_by: Option<String>,
+    #[serde(default)]
+    created: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletion {
+    #[serde(default)]
+    choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChoiceToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceToolCall {
+    #[serde(default)]
+    id: Option<String>,
+    function: ChoiceFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceFunction {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    #[serde(default)]
+    prompt_tokens: Option<u32>,
+    #[serde(default)]
+    completion_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChunkToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkToolCall {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<ChunkFunction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkFunction {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+/// Tool call assembled from streamed fragments
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: Option<String>,
+    name: String,
+    arguments: String,
+}
+
+const ERROR_BODY_LIMIT: usize = 280;
+
+fn trim_error_body(body: &str) -> String {
+    let trimmed = body.trim();
+    let mut truncated = trimmed.chars().take(ERROR_BODY_LIMIT).collect::<String>();
+    if trimmed.chars().count() > ERROR_BODY_LIMIT {
+        truncated.push('…');
+    }
+    truncated
+}
+
+fn extract_error_message(payload: &Value) -> Option<String> {
+    payload
+        .pointer("/error/message")
+        .or_else(|| payload.get("error"))
+        .or_else(|| payload.get("message"))
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+}
+
+/// Build a tool call from the model's JSON-encoded arguments
+fn to_tool_call(id: Option<String>, name: String, arguments: &str) -> ToolCall {
+    let arguments = if arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(arguments).unwrap_or_else(|_| Value::String(arguments.to_string()))
+    };
+
+    ToolCall {
+        id: id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        tool_name: name,
+        arguments,
+        status: ToolCallStatus::Pending,
+        result: None,
+        error_message: None,
+        execution_time_ms: None,
+    }
+}
+
+fn message_content(message: &Message) -> Value {
+    let images: Vec<Value> = message
+        .attachments
+        .iter()
+        .filter(|attachment| attachment.mime_type.starts_with("image/"))
+        .map(|attachment| {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(&attachment.data);
+            json!({
+                "type": "image_url",
+                "image_url": {
+                    "url": format!("data:{};base64,{}", attachment.mime_type, encoded),
+                },
+            })
+        })
+        .collect();
+
+    if images.is_empty() {
+        return json!(message.content);
+    }
+
+    let mut parts = vec![json!({ "type": "text", "text": message.content })];
+    parts.extend(images);
+    Value::Array(parts)
+}
+
+/// Convert app messages to OpenAI chat messages, including tool call history
+fn messages_to_openai(messages: &[Message], system_prompt: Option<&str>) -> Vec<Value> {
+    let mut openai_messages = Vec::new();
+
+    if let Some(prompt) = system_prompt.filter(|prompt| !prompt.trim().is_empty()) {
+        openai_messages.push(json!({ "role": "system", "content": prompt }));
+    }
+
+    for message in messages {
+        let value = match message.role {
+            MessageRole::System => json!({ "role": "system", "content": message.content }),
+            MessageRole::User => json!({ "role": "user", "content": message_content(message) }),
+            MessageRole::Assistant if !message.tool_calls.is_empty() => {
+                let tool_calls: Vec<Value> = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": {
+                                "name": call.tool_name,
+                                "arguments": call.arguments.to_string(),
+                            },
+                        })
+                    })
+                    .collect();
+                let content = (!message.content.is_empty()).then_some(&message.content);
+                json!({ "role": "assistant", "content": content, "tool_calls": tool_calls })
+            }
+            MessageRole::Assistant => json!({ "role": "assistant", "content": message.content }),
+            MessageRole::Tool => json!({
+                "role": "tool",
+                "tool_call_id": message.tool_calls.first().map(|call| call.id.as_str()),
+                "content": message.content,
+            }),
+        };
+        openai_messages.push(value);
+    }
+
+    openai_messages
+}
+
+fn to_ollama_model(model: ModelEntry) -> OllamaModel {
+    OllamaModel {
+        modified_at: model
+            .created
+            .and_then(|created| chrono::DateTime::from_timestamp(created, 0))
+            .map(|created| created.to_rfc3339()),
+        name: model.id,
+        size: None,
+        digest: None,
+        details: Some(OllamaModelDetails {
+            format: Some("openai".to_string()),
+            family: model.owned_by,
+            families: None,
+            parameter_size: None,
+            quantization_level: None,
+        }),
+        connection_id: None,
+    }
+}
+
+impl OpenAiCompatClient {
+    pub fn new(base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .build()
+                .unwrap_or_default(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty()),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let request = self.client.request(method, url);
+
+        if let Some(api_key) = &self.api_key {
+            request.bearer_auth(api_key)
+        } else {
+            request
+        }
+    }
+
+    fn transport_error(&self, action: &str, error: reqwest::Error) -> String {
+        if error.is_timeout() {
+            return format!("{action} timed out while contacting {}", self.base_url);
+        }
+        if error.is_connect() {
+            return format!(
+                "{action} could not connect to {}. Verify the API base URL.",
+                self.base_url
+            );
+        }
+
+        format!("{action} failed: {error}")
+    }
+
+    fn status_error(&self, action: &str, status: StatusCode, body: &str) -> String {
+        if matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return "Authentication failed. Check the configured API key.".to_string();
+        }
+
+        let detail = serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|payload| extract_error_message(&payload))
+            .unwrap_or_else(|| trim_error_body(body));
+        if detail.is_empty() {
+            return format!("{action} failed with status: {status}");
+        }
+
+        format!("{action} failed ({status}): {detail}")
+    }
+
+    /// Whether this is OpenAI's own API rather than a compatible server
+    fn is_openai(&self) -> bool {
+        reqwest::Url::parse(&self.base_url)
+            .is_ok_and(|url| url.host_str() == Some("api.openai.com"))
+    }
+
+    fn chat_body(&self, request: &BackendChatRequest, stream: bool) -> Value {
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages_to_openai(&request.messages, request.system_prompt.as_deref()),
+            "temperature": request.parameters.temperature.clamp(0.0, 2.0),
+            "stream": stream,
+        });
+
+        if let Some(top_p) = request.parameters.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = request.parameters.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if let Some(seed) = request.parameters.seed {
+            body["seed"] = json!(seed);
+        }
+        // Sampling extensions: llama.cpp reads `repeat_penalty`, vLLM and
+        // OpenRouter read `repetition_penalty`; all three accept `min_p`.
+        // OpenAI rejects unknown fields and has no equivalent, so it gets neither.
+        if !self.is_openai() {
+            if let Some(repeat_penalty) = request.parameters.repeat_penalty {
+                body["repeat_penalty"] = json!(repeat_penalty);
+                body["repetition_penalty"] = json!(repeat_penalty);
+            }
+            if let Some(min_p) = request.parameters.min_p {
+                body["min_p"] = json!(min_p);
+            }
+        }
+        if let Some(stop) = request.parameters.stop_sequences() {
+            body["stop"] = json!(stop);
+        }
+        if let Some(format) = &request.response_format {
+            body["response_format"] = format.openai_response_format();
+        }
+        if stream {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        if !request.tools.is_empty() {
+            let tools: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        },
+                    })
+                })
+                .collect();
+            body["tools"] = Value::Array(tools);
+        }
+
+        body
+    }
+
+    async fn send_chat(
+        &self,
+        action: &str,
+        body: &Value,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        let mut request = self.request(Method::POST, "/chat/completions").json(body);
+        if stream {
+            request = request.header("Accept", "text/event-stream");
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| self.transport_error(action, error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error(action, status, &body));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiCompatClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::OpenAiCompat
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: true,
+            model_details: false,
+            model_management: false,
+            sessions: false,
+            tools: true,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        ChatBackend::list_models(self).await.map(|_| true)
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let response = self
+            .request(Method::GET, "/models")
+            .send()
+            .await
+            .map_err(|error| self.transport_error("Model listing", error))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error("Model listing", status, &body));
+        }
+
+        let models: ModelsResponse = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse model list: {}", error))?;
+
+        Ok(models.data.into_iter().map(to_ollama_model).collect())
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let body = self.chat_body(request, false);
+        let response = self.send_chat("Chat request", &body, false).await?;
+
+        let completion: ChatCompletion = response
+            .json()
+            .await
+            .map_err(|error| format!("Failed to parse chat response: {}", error))?;
+
+        let message = completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| "Chat response contained no choices".to_string())?;
+
+        let usage = completion.usage;
+        Ok(BackendChatResponse {
+            content: message.content.unwrap_or_default(),
+            prompt_eval_count: usage.as_ref().and_then(|usage| usage.prompt_tokens),
+            eval_count: usage.as_ref().and_then(|usage| usage.completion_tokens),
+            tool_calls: message
+                .tool_calls
+                .into_iter()
+                .map(|call| to_tool_call(call.id, call.function.name, &call.function.arguments))
+                .collect(),
+            ..BackendChatResponse::default()
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let body = self.chat_body(request, true);
+        let response = self
+            .send_chat("Streaming chat request", &body, true)
+            .await?;
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut result = BackendChatResponse::default();
+        // Tool call fragments keyed by their index in the response
+        let mut tool_calls: BTreeMap<usize, PartialToolCall> = BTreeMap::new();
+
+        'stream: while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|error| format!("Stream error: {}", error))?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(newline_pos) = buffer.find('\n') {
+                let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
+                buffer = buffer[newline_pos + 1..].to_string();
+
+                let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                    continue;
+                };
+                if data.is_empty() {
+                    continue;
+                }
+                if data == "[DONE]" {
+                    break 'stream;
+                }
+
+                let payload: Value = serde_json::from_str(data)
+                    .map_err(|error| format!("Failed to parse stream chunk: {}", error))?;
+                if payload.get("error").is_some() {
+                    return Err(extract_error_message(&payload)
+                        .unwrap_or_else(|| "The server returned a streaming error".to_string()));
+                }
+
+                let chunk: ChatChunk = serde_json::from_value(payload)
+                    .map_err(|error| format!("Failed to parse stream chunk: {}", error))?;
+                if let Some(usage) = chunk.usage {
+                    result.prompt_eval_count = usage.prompt_tokens.or(result.prompt_eval_count);
+                    result.eval_count = usage.completion_tokens.or(result.eval_count);
+                }
+
+                for choice in chunk.choices {
+                    if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                        result.content.push_str(&content);
+                        on_chunk(StreamChunk::Content(&content));
+                    }
+
+                    for fragment in choice.delta.tool_calls {
+                        let call = tool_calls.entry(fragment.index).or_default();
+                        if fragment.id.is_some() {
+                            call.id = fragment.id;
+                        }
+                        if let Some(function) = fragment.function {
+                            if let Some(name) = function.name {
+                                call.name.push_str(&name);
+                            }
+                            if let Some(arguments) = function.arguments {
+                                call.arguments.push_str(&arguments);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        result.tool_calls = tool_calls
+            .into_values()
+            .filter(|call| !call.name.is_empty())
+            .map(|call| to_tool_call(call.id, call.name, &call.arguments))
+            .collect();
+
+        Ok(result)
+    }
+}
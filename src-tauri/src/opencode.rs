@@ -1,11 +1,27 @@
+use crate::backend::{
+    latest_user_text, simple_title, BackendCapabilities, BackendChatRequest, BackendChatResponse,
+    BackendKind, BackendSession, ChatBackend, ChunkCallback, PermissionRequest, PermissionResponse,
+    StreamChunk,
+};
+use crate::models::{
+    Message, MessageRole, MessageStatus, OllamaModel, OllamaModelDetails, ToolCall, ToolCallStatus,
+    ToolResult,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::Either;
+use futures::StreamExt;
 use reqwest::{Client, Method, RequestBuilder, StatusCode};
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Clone)]
 pub struct OpencodeClient {
     client: Client,
+    /// Client without a request or read timeout, for agent runs and the event
+    /// stream; a run can sit idle for as long as a permission request is pending
+    stream_client: Client,
     base_url: String,
     auth: Option<OpencodeAuth>,
 }
@@ -32,6 +48,14 @@ pub struct OpencodeModelRef {
 #[derive(Debug, Clone)]
 pub struct OpencodePromptResult {
     pub content: String,
+    /// Text of the `reasoning` parts, kept out of `content`
+    pub reasoning: Option<String>,
+    /// Tools the agent ran while answering, in the order they started
+    pub tool_calls: Vec<ToolCall>,
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    /// Cost in USD as computed by OpenCode for its provider
+    pub cost: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -42,6 +66,11 @@ struct ModelSelection<'a> {
     model_id: &'a str,
 }
 
+#[derive(Serialize)]
+struct PermissionReply {
+    response: &'static str,
+}
+
 #[derive(Serialize)]
 struct TextPart<'a> {
     #[serde(rename = "type")]
@@ -74,6 +103,19 @@ fn trim_error_body(body: &str) -> String {
     truncated
 }
 
+/// Parse a `provider/model` selector into an OpenCode model reference
+fn parse_model_selector(model: &str) -> Option<OpencodeModelRef> {
+    let (provider_id, model_id) = model.split_once('/')?;
+    if provider_id.trim().is_empty() || model_id.trim().is_empty() {
+        return None;
+    }
+
+    Some(OpencodeModelRef {
+        provider_id: provider_id.to_string(),
+        model_id: model_id.to_string(),
+    })
+}
+
 fn extract_prompt_error(payload: &Value) -> Option<String> {
     for pointer in [
         "/info/error/data/message",
@@ -100,6 +142,25 @@ fn extract_prompt_error(payload: &Value) -> Option<String> {
         .map(|name| name.to_string())
 }
 
+/// Token usage and cost from the assistant message info of a prompt response
+fn prompt_result(payload: &Value, content: String) -> OpencodePromptResult {
+    let tokens = |name: &str| {
+        payload
+            .pointer(&format!("/info/tokens/{name}"))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    };
+
+    OpencodePromptResult {
+        content,
+        reasoning: extract_prompt_reasoning(payload),
+        tool_calls: extract_prompt_tool_calls(payload),
+        input_tokens: tokens("input"),
+        output_tokens: tokens("output"),
+        cost: payload.pointer("/info/cost").and_then(|v| v.as_f64()),
+    }
+}
+
 fn extract_prompt_content(payload: &Value) -> String {
     if let Some(text) = payload
         .as_str()
@@ -118,34 +179,22 @@ fn extract_prompt_content(payload: &Value) -> String {
         }
     }
 
+    // Reasoning and tool parts are returned separately
     if let Some(parts) = payload.get("parts").and_then(|v| v.as_array()) {
-        let mut primary = Vec::new();
-        let mut fallback = Vec::new();
-
-        for part in parts {
-            let part_type = part.get("type").and_then(|t| t.as_str()).unwrap_or_default();
-            let part_text = part
-                .get("text")
-                .and_then(|v| v.as_str())
-                .or_else(|| part.get("content").and_then(|v| v.as_str()))
-                .or_else(|| part.pointer("/state/output").and_then(|v| v.as_str()))
-                .map(str::trim)
-                .filter(|text| !text.is_empty());
-
-            if let Some(text) = part_text {
-                match part_type {
-                    "text" => primary.push(text.to_string()),
-                    "reasoning" | "tool" => fallback.push(text.to_string()),
-                    _ => {}
-                }
-            }
-        }
+        let text: String = parts
+            .iter()
+            .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|part| {
+                part.get("text")
+                    .and_then(|v| v.as_str())
+                    .or_else(|| part.get("content").and_then(|v| v.as_str()))
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+            })
+            .collect();
 
-        if !primary.is_empty() {
-            return primary.join("");
-        }
-        if !fallback.is_empty() {
-            return fallback.join("\n");
+        if !text.is_empty() {
+            return text;
         }
     }
 
@@ -163,6 +212,45 @@ fn extract_prompt_content(payload: &Value) -> String {
     String::new()
 }
 
+/// Tool calls from the `tool` parts of a prompt response
+fn extract_prompt_tool_calls(payload: &Value) -> Vec<ToolCall> {
+    if let Some(items) = payload.as_array() {
+        return items.iter().flat_map(extract_prompt_tool_calls).collect();
+    }
+
+    payload
+        .get("parts")
+        .and_then(|v| v.as_array())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("tool"))
+                .filter_map(tool_call_from_part)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Join the `reasoning` parts of a prompt response
+fn extract_prompt_reasoning(payload: &Value) -> Option<String> {
+    if let Some(items) = payload.as_array() {
+        return items.iter().rev().find_map(extract_prompt_reasoning);
+    }
+
+    let reasoning = payload
+        .get("parts")
+        .and_then(|v| v.as_array())?
+        .iter()
+        .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("reasoning"))
+        .filter_map(|part| part.get("text").and_then(|v| v.as_str()))
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    (!reasoning.is_empty()).then_some(reasoning)
+}
+
 impl OpencodeClient {
     pub fn new(base_url: &str) -> Self {
         Self {
@@ -170,6 +258,10 @@ impl OpencodeClient {
                 .timeout(std::time::Duration::from_secs(300))
                 .build()
                 .unwrap_or_default(),
+            stream_client: Client::builder()
+                .connect_timeout(std::time::Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
             base_url: base_url.trim_end_matches('/').to_string(),
             auth: None,
         }
@@ -193,8 +285,12 @@ impl OpencodeClient {
     }
 
     fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request_with(&self.client, method, path)
+    }
+
+    fn request_with(&self, client: &Client, method: Method, path: &str) -> RequestBuilder {
         let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
-        let request = self.client.request(method, url);
+        let request = client.request(method, url);
         if let Some(auth) = &self.auth {
             request.basic_auth(auth.username.clone(), Some(auth.password.clone()))
         } else {
@@ -416,8 +512,13 @@ impl OpencodeClient {
             }],
         };
 
+        // Agent runs can take longer than the regular request timeout
         let response = self
-            .request(Method::POST, &format!("/session/{session_id}/message"))
+            .request_with(
+                &self.stream_client,
+                Method::POST,
+                &format!("/session/{session_id}/message"),
+            )
             .json(&request)
             .send()
             .await
@@ -449,13 +550,19 @@ impl OpencodeClient {
                 }
                 return Ok(OpencodePromptResult {
                     content: trimmed_body.to_string(),
+                    reasoning: None,
+                    tool_calls: Vec::new(),
+                    input_tokens: None,
+                    output_tokens: None,
+                    cost: None,
                 });
             }
         };
 
-        let content = extract_prompt_content(&payload);
-        if !content.is_empty() {
-            return Ok(OpencodePromptResult { content });
+        let result = prompt_result(&payload, extract_prompt_content(&payload));
+        if !result.content.is_empty() || result.reasoning.is_some() || !result.tool_calls.is_empty()
+        {
+            return Ok(result);
         }
 
         if let Some(message) = extract_prompt_error(&payload) {
@@ -464,4 +571,735 @@ impl OpencodeClient {
 
         Err("OpenCode prompt response did not include any text content".to_string())
     }
+
+    /// Subscribe to the server's event stream, which covers every session
+    pub async fn subscribe_events(&self) -> Result<reqwest::Response, String> {
+        let response = self
+            .request_with(&self.stream_client, Method::GET, "/event")
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+            .map_err(|e| self.transport_error("OpenCode event stream", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error("OpenCode event stream", status, &body));
+        }
+        Ok(response)
+    }
+
+    /// Prompt a session while passing on its text, reasoning and tool parts
+    /// from the event stream as they are updated.
+    ///
+    /// The prompt response stays the source of truth: anything the event stream
+    /// missed is emitted from it at the end.
+    pub async fn prompt_session_streaming(
+        &self,
+        session_id: &str,
+        text: &str,
+        model: Option<&OpencodeModelRef>,
+        system: Option<&str>,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<OpencodePromptResult, String> {
+        // Subscribe first so no event of this prompt is missed
+        let events = self.subscribe_events().await?;
+        let mut stream = PromptStream::new(session_id);
+
+        let mut result = {
+            let prompt = self.prompt_session(session_id, text, model, system);
+            let watch = stream.follow(events, on_chunk);
+            futures::pin_mut!(prompt, watch);
+            match futures::future::select(prompt, watch).await {
+                Either::Left((result, _)) => result,
+                // The session went idle or the event stream ended
+                Either::Right(((), prompt)) => prompt.await,
+            }
+        }?;
+
+        let unsent = |full: &str, streamed: &str| {
+            full.strip_prefix(streamed.trim())
+                .filter(|rest| !rest.is_empty())
+                .map(|rest| rest.to_string())
+        };
+        let reasoning = result.reasoning.as_deref().unwrap_or_default();
+        if let Some(rest) = unsent(reasoning, &stream.reasoning) {
+            on_chunk(StreamChunk::Reasoning(&rest));
+        }
+        if let Some(rest) = unsent(&result.content, &stream.content) {
+            on_chunk(StreamChunk::Content(&rest));
+        }
+
+        // Final states of calls whose last update the event stream missed
+        for call in &result.tool_calls {
+            let streamed = stream
+                .tool_calls
+                .iter()
+                .find(|streamed| streamed.id == call.id);
+            if streamed.is_none_or(|streamed| streamed.status != call.status) {
+                on_chunk(StreamChunk::ToolCall(call));
+            }
+        }
+
+        if result.tool_calls.is_empty() {
+            result.tool_calls = stream.tool_calls;
+        }
+        Ok(result)
+    }
+
+    /// Send a request and parse its JSON response
+    async fn send_json(&self, request: RequestBuilder, action: &str) -> Result<Value, String> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| self.transport_error(action, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.status_error(action, status, &body));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse {action} response: {e}"))
+    }
+
+    /// Every session on the server, including ones started from the terminal
+    pub async fn list_sessions(&self) -> Result<Vec<Value>, String> {
+        let payload = self
+            .send_json(
+                self.request(Method::GET, "/session"),
+                "OpenCode session listing",
+            )
+            .await?;
+        payload
+            .as_array()
+            .cloned()
+            .ok_or_else(|| "OpenCode session listing did not return a list".to_string())
+    }
+
+    /// Messages of a session, each with its `info` and `parts`
+    pub async fn session_messages(&self, session_id: &str) -> Result<Vec<Value>, String> {
+        let payload = self
+            .send_json(
+                self.request(Method::GET, &format!("/session/{session_id}/message")),
+                "OpenCode session history",
+            )
+            .await?;
+        payload
+            .as_array()
+            .cloned()
+            .ok_or_else(|| "OpenCode session history did not return a list".to_string())
+    }
+
+    pub async fn abort_session(&self, session_id: &str) -> Result<bool, String> {
+        let payload = self
+            .send_json(
+                self.request(Method::POST, &format!("/session/{session_id}/abort")),
+                "OpenCode session abort",
+            )
+            .await?;
+        Ok(payload.as_bool().unwrap_or(true))
+    }
+
+    pub async fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        self.send_json(
+            self.request(Method::DELETE, &format!("/session/{session_id}")),
+            "OpenCode session delete",
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Share a session or stop sharing it, returning the updated session
+    pub async fn share_session(&self, session_id: &str, shared: bool) -> Result<Value, String> {
+        let method = if shared { Method::POST } else { Method::DELETE };
+        self.send_json(
+            self.request(method, &format!("/session/{session_id}/share")),
+            "OpenCode session share",
+        )
+        .await
+    }
+
+    /// Compact a session with a summary written by `model`
+    pub async fn summarize_session(
+        &self,
+        session_id: &str,
+        model: &OpencodeModelRef,
+    ) -> Result<(), String> {
+        // Summarising runs the model, so it may outlast the regular timeout
+        let request = self
+            .request_with(
+                &self.stream_client,
+                Method::POST,
+                &format!("/session/{session_id}/summarize"),
+            )
+            .json(&ModelSelection {
+                provider_id: &model.provider_id,
+                model_id: &model.model_id,
+            });
+        self.send_json(request, "OpenCode session summarize")
+            .await
+            .map(|_| ())
+    }
+
+    /// Answer a pending permission request so the agent run can continue
+    pub async fn respond_permission(
+        &self,
+        session_id: &str,
+        permission_id: &str,
+        response: PermissionResponse,
+    ) -> Result<(), String> {
+        let response = match response {
+            PermissionResponse::Allow => "once",
+            PermissionResponse::Always => "always",
+            PermissionResponse::Deny => "reject",
+        };
+        self.send_json(
+            self.request(
+                Method::POST,
+                &format!("/session/{session_id}/permissions/{permission_id}"),
+            )
+            .json(&PermissionReply { response }),
+            "OpenCode permission response",
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+/// Read a permission event, which newer servers send as `permission.asked`
+/// with `permission`/`patterns` in place of `type`/`pattern`
+fn permission_from_opencode(permission: &Value) -> Option<PermissionRequest> {
+    let text = |pointer: &str| {
+        permission
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .map(|text| text.to_string())
+    };
+    let strings = |value: &Value| match value {
+        Value::String(pattern) => vec![pattern.clone()],
+        Value::Array(patterns) => patterns
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|pattern| pattern.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let permission_type = text("/type").or_else(|| text("/permission"))?;
+    let patterns = permission
+        .get("pattern")
+        .or_else(|| permission.get("patterns"))
+        .map(strings)
+        .unwrap_or_default();
+    Some(PermissionRequest {
+        id: text("/id")?,
+        session_id: text("/sessionID")?,
+        title: text("/title").unwrap_or_else(|| permission_type.clone()),
+        permission_type,
+        patterns,
+        call_id: text("/callID").or_else(|| text("/tool/callID")),
+        metadata: permission.get("metadata").cloned().unwrap_or(Value::Null),
+    })
+}
+
+/// Convert an OpenCode millisecond timestamp
+fn timestamp(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|v| v.as_i64())
+        .and_then(DateTime::from_timestamp_millis)
+}
+
+fn session_from_opencode(session: &Value) -> BackendSession {
+    let text = |pointer: &str| {
+        session
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .filter(|text| !text.is_empty())
+            .map(|text| text.to_string())
+    };
+
+    BackendSession {
+        id: text("/id").unwrap_or_default(),
+        title: text("/title").unwrap_or_default(),
+        created_at: timestamp(session.pointer("/time/created")),
+        updated_at: timestamp(session.pointer("/time/updated")),
+        parent_id: text("/parentID"),
+        share_url: text("/share/url"),
+        directory: text("/directory"),
+    }
+}
+
+/// Convert a session history entry into an app message
+fn message_from_opencode(entry: &Value) -> Option<Message> {
+    let info = entry.get("info")?;
+    let role = match info.get("role").and_then(|v| v.as_str())? {
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        _ => return None,
+    };
+    // Synthetic parts are context OpenCode added, such as file contents
+    let parts: Vec<&Value> = entry
+        .get("parts")
+        .and_then(|v| v.as_array())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| part.get("synthetic").and_then(|v| v.as_bool()) != Some(true))
+                .collect()
+        })
+        .unwrap_or_default();
+    let text_of = |kind: &str| {
+        parts
+            .iter()
+            .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some(kind))
+            .filter_map(|part| part.get("text").and_then(|v| v.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    let tokens = |name: &str| {
+        info.pointer(&format!("/tokens/{name}"))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32)
+    };
+    let error = info.get("error").map(|error| {
+        extract_prompt_error(&serde_json::json!({ "error": error }))
+            .unwrap_or_else(|| "OpenCode reported an error".to_string())
+    });
+    let reasoning = text_of("reasoning");
+
+    Some(Message {
+        id: info
+            .get("id")
+            .and_then(|v| v.as_str())
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        role,
+        content: text_of("text"),
+        timestamp: timestamp(info.pointer("/time/created")).unwrap_or_else(Utc::now),
+        model_name: info
+            .get("modelID")
+            .and_then(|v| v.as_str())
+            .map(|model| model.to_string()),
+        is_error: error.is_some(),
+        token_count: tokens("output"),
+        prompt_token_count: tokens("input"),
+        cost: info.get("cost").and_then(|v| v.as_f64()),
+        attachments: Vec::new(),
+        tool_calls: parts
+            .iter()
+            .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("tool"))
+            .filter_map(|part| tool_call_from_part(part))
+            .collect(),
+        status: if error.is_some() {
+            MessageStatus::Failed
+        } else {
+            MessageStatus::Sent
+        },
+        status_message: error,
+        reasoning: (!reasoning.is_empty()).then_some(reasoning),
+    })
+}
+
+/// Progress of one prompt, rebuilt from `/event` updates for its session
+struct PromptStream {
+    session_id: String,
+    /// Messages sent by the user, whose parts are not part of the response
+    user_messages: HashSet<String>,
+    /// Length of the text already passed on, per part
+    emitted: HashMap<String, usize>,
+    content: String,
+    reasoning: String,
+    /// Latest state of each tool call, in the order they started
+    tool_calls: Vec<ToolCall>,
+}
+
+impl PromptStream {
+    fn new(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            user_messages: HashSet::new(),
+            emitted: HashMap::new(),
+            content: String::new(),
+            reasoning: String::new(),
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// Read server-sent events until the session goes idle or the stream ends
+    async fn follow(&mut self, events: reqwest::Response, on_chunk: &mut ChunkCallback<'_>) {
+        let mut bytes = events.bytes_stream();
+        let mut buffer = String::new();
+        let mut data = String::new();
+
+        while let Some(Ok(chunk)) = bytes.next().await {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(newline_pos) = buffer.find('\n') {
+                let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
+                buffer.drain(..=newline_pos);
+
+                if let Some(data_line) = line.strip_prefix("data:") {
+                    if !data.is_empty() {
+                        data.push('\n');
+                    }
+                    data.push_str(data_line.trim());
+                } else if line.is_empty() && !data.is_empty() {
+                    let event = serde_json::from_str::<Value>(&data);
+                    data.clear();
+                    if let Ok(event) = event {
+                        if self.handle_event(&event, on_chunk) {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply one event, returning whether the session has gone idle
+    fn handle_event(&mut self, event: &Value, on_chunk: &mut ChunkCallback<'_>) -> bool {
+        let properties = event.get("properties").unwrap_or(&Value::Null);
+        let in_session = |value: Option<&Value>| {
+            value.and_then(|v| v.as_str()) == Some(self.session_id.as_str())
+        };
+
+        match event
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+        {
+            "message.updated" => {
+                let info = properties.get("info").unwrap_or(&Value::Null);
+                if in_session(info.get("sessionID"))
+                    && info.get("role").and_then(|v| v.as_str()) == Some("user")
+                {
+                    if let Some(id) = info.get("id").and_then(|v| v.as_str()) {
+                        self.user_messages.insert(id.to_string());
+                    }
+                }
+            }
+            "message.part.updated" => {
+                let part = properties.get("part").unwrap_or(&Value::Null);
+                let from_user = part
+                    .get("messageID")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|id| self.user_messages.contains(id));
+                if in_session(part.get("sessionID")) && !from_user {
+                    self.apply_part(part, on_chunk);
+                }
+            }
+            "permission.updated" | "permission.asked" => {
+                if let Some(permission) = permission_from_opencode(properties) {
+                    if permission.session_id == self.session_id {
+                        on_chunk(StreamChunk::PermissionRequest(&permission));
+                    }
+                }
+            }
+            "session.idle" => return in_session(properties.get("sessionID")),
+            "session.status" => {
+                return in_session(properties.get("sessionID"))
+                    && properties.pointer("/status/type").and_then(|v| v.as_str()) == Some("idle");
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn apply_part(&mut self, part: &Value, on_chunk: &mut ChunkCallback<'_>) {
+        let part_id = part.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+        match part
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+        {
+            kind @ ("text" | "reasoning") => {
+                if part.get("synthetic").and_then(|v| v.as_bool()) == Some(true) {
+                    return;
+                }
+                let text = part
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let emitted = self.emitted.entry(part_id.to_string()).or_default();
+                // Parts carry their full text so far; pass on what is new
+                let Some(delta) = text.get(*emitted..).filter(|delta| !delta.is_empty()) else {
+                    return;
+                };
+                *emitted = text.len();
+                if kind == "text" {
+                    self.content.push_str(delta);
+                    on_chunk(StreamChunk::Content(delta));
+                } else {
+                    self.reasoning.push_str(delta);
+                    on_chunk(StreamChunk::Reasoning(delta));
+                }
+            }
+            "tool" => {
+                let Some(call) = tool_call_from_part(part) else {
+                    return;
+                };
+                on_chunk(StreamChunk::ToolCall(&call));
+                match self
+                    .tool_calls
+                    .iter_mut()
+                    .find(|existing| existing.id == call.id)
+                {
+                    Some(existing) => *existing = call,
+                    None => self.tool_calls.push(call),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Map an OpenCode `tool` part to a tool call the agent ran
+fn tool_call_from_part(part: &Value) -> Option<ToolCall> {
+    let tool_name = part.get("tool").and_then(|v| v.as_str())?.to_string();
+    let state = part.get("state").unwrap_or(&Value::Null);
+    let status = match state.get("status").and_then(|v| v.as_str()) {
+        Some("running") => ToolCallStatus::Running,
+        Some("completed") => ToolCallStatus::Completed,
+        Some("error") => ToolCallStatus::Failed,
+        _ => ToolCallStatus::Pending,
+    };
+    let error_message = state
+        .get("error")
+        .and_then(|v| v.as_str())
+        .map(|error| error.to_string());
+
+    let result = match status {
+        ToolCallStatus::Completed => {
+            let mut data = serde_json::json!({
+                "output": state.get("output").cloned().unwrap_or(Value::Null),
+            });
+            // Edits report the unified diff and the file they changed
+            let metadata = state.get("metadata").unwrap_or(&Value::Null);
+            if let Some(diff) = metadata.get("diff").filter(|diff| diff.is_string()) {
+                data["diff"] = diff.clone();
+            }
+            if let Some(file) = metadata
+                .pointer("/filediff/file")
+                .or_else(|| metadata.get("filepath"))
+                .or_else(|| state.pointer("/input/filePath"))
+                .filter(|file| file.is_string())
+            {
+                data["file"] = file.clone();
+            }
+
+            Some(ToolResult {
+                success: true,
+                data,
+                summary: state
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .map(|title| title.to_string()),
+            })
+        }
+        ToolCallStatus::Failed => error_message.clone().map(ToolResult::failure),
+        _ => None,
+    };
+
+    // Start and end are Unix timestamps in milliseconds
+    let time = |name: &str| {
+        state
+            .pointer(&format!("/time/{name}"))
+            .and_then(|v| v.as_u64())
+    };
+    let execution_time_ms = match (time("start"), time("end")) {
+        (Some(start), Some(end)) => Some(end.saturating_sub(start)),
+        _ => None,
+    };
+
+    Some(ToolCall {
+        id: part
+            .get("callID")
+            .or_else(|| part.get("id"))
+            .and_then(|v| v.as_str())
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        tool_name,
+        arguments: state
+            .get("input")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({})),
+        status,
+        result,
+        error_message,
+        execution_time_ms,
+    })
+}
+
+#[async_trait]
+impl ChatBackend for OpencodeClient {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Opencode
+    }
+
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            streaming: true,
+            vision: false,
+            model_details: false,
+            model_management: false,
+            sessions: true,
+            tools: false,
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool, String> {
+        OpencodeClient::health_check(self).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let models = OpencodeClient::list_models(self).await?;
+        Ok(models
+            .into_iter()
+            .map(|m| OllamaModel {
+                name: format!("{}/{}", m.provider_id, m.model_id),
+                modified_at: None,
+                size: None,
+                digest: None,
+                details: Some(OllamaModelDetails {
+                    format: Some("opencode".to_string()),
+                    family: Some(m.provider_id),
+                    families: None,
+                    parameter_size: None,
+                    quantization_level: Some(m.display_name),
+                }),
+                connection_id: None,
+            })
+            .collect())
+    }
+
+    async fn chat(&self, request: &BackendChatRequest) -> Result<BackendChatResponse, String> {
+        let user_text = latest_user_text(&request.messages)
+            .ok_or_else(|| "No user message found to send".to_string())?;
+
+        let model_selection = parse_model_selector(&request.model);
+        let session_id = match &request.session_id {
+            Some(id) => id.clone(),
+            None => self.create_session(None).await?,
+        };
+
+        let response = self
+            .prompt_session(
+                &session_id,
+                &user_text,
+                model_selection.as_ref(),
+                request.system_prompt.as_deref(),
+            )
+            .await?;
+
+        Ok(BackendChatResponse {
+            content: response.content,
+            reasoning: response.reasoning,
+            session_id: Some(session_id),
+            prompt_eval_count: response.input_tokens,
+            eval_count: response.output_tokens,
+            cost: response.cost,
+            tool_calls: response.tool_calls,
+            ..BackendChatResponse::default()
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &BackendChatRequest,
+        on_chunk: &mut ChunkCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let user_text = latest_user_text(&request.messages)
+            .ok_or_else(|| "No user message found to send".to_string())?;
+
+        let model_selection = parse_model_selector(&request.model);
+        let session_id = match &request.session_id {
+            Some(id) => id.clone(),
+            None => self.create_session(None).await?,
+        };
+
+        let response = self
+            .prompt_session_streaming(
+                &session_id,
+                &user_text,
+                model_selection.as_ref(),
+                request.system_prompt.as_deref(),
+                on_chunk,
+            )
+            .await?;
+
+        Ok(BackendChatResponse {
+            content: response.content,
+            reasoning: response.reasoning,
+            session_id: Some(session_id),
+            prompt_eval_count: response.input_tokens,
+            eval_count: response.output_tokens,
+            cost: response.cost,
+            tool_calls: response.tool_calls,
+            ..BackendChatResponse::default()
+        })
+    }
+
+    async fn create_session(&self) -> Result<String, String> {
+        OpencodeClient::create_session(self, None).await
+    }
+
+    async fn list_sessions(&self) -> Result<Vec<BackendSession>, String> {
+        let mut sessions: Vec<BackendSession> = OpencodeClient::list_sessions(self)
+            .await?
+            .iter()
+            .map(session_from_opencode)
+            .filter(|session| !session.id.is_empty())
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+        Ok(sessions)
+    }
+
+    async fn session_messages(&self, session_id: &str) -> Result<Vec<Message>, String> {
+        Ok(OpencodeClient::session_messages(self, session_id)
+            .await?
+            .iter()
+            .filter_map(message_from_opencode)
+            .collect())
+    }
+
+    async fn abort_session(&self, session_id: &str) -> Result<bool, String> {
+        OpencodeClient::abort_session(self, session_id).await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        OpencodeClient::delete_session(self, session_id).await
+    }
+
+    async fn share_session(
+        &self,
+        session_id: &str,
+        shared: bool,
+    ) -> Result<BackendSession, String> {
+        let session = OpencodeClient::share_session(self, session_id, shared).await?;
+        Ok(session_from_opencode(&session))
+    }
+
+    async fn summarize_session(&self, session_id: &str, model: &str) -> Result<(), String> {
+        let model = parse_model_selector(model)
+            .ok_or_else(|| format!("Expected a provider/model selector, got '{model}'"))?;
+        OpencodeClient::summarize_session(self, session_id, &model).await
+    }
+
+    async fn respond_permission(
+        &self,
+        session_id: &str,
+        permission_id: &str,
+        response: PermissionResponse,
+    ) -> Result<(), String> {
+        OpencodeClient::respond_permission(self, session_id, permission_id, response).await
+    }
+
+    /// Titles stay local: a side request would spawn a full agent session.
+    async fn generate_title(&self, _model: &str, first_message: &str) -> Result<String, String> {
+        Ok(simple_title(first_message))
+    }
 }
@@ -0,0 +1,44 @@
+//! Credentials kept in the OS keychain.
+//!
+//! Saved connection and cloud provider files only hold the keychain account
+//! of each secret, so passwords, tokens and API keys never reach disk in plain
+//! text.
+
+use keyring::Entry;
+
+/// Keychain service every entry of the app is stored under
+const SERVICE: &str = "private-chat-hub";
+
+/// Store `secret` under `account` and return the account to save as its
+/// reference. Without a secret any stored entry is removed and there is no
+/// reference.
+pub fn save(account: String, secret: Option<&str>) -> Result<Option<String>, String> {
+    let Some(secret) = secret.filter(|secret| !secret.is_empty()) else {
+        delete(&account);
+        return Ok(None);
+    };
+    entry(&account)?
+        .set_password(secret)
+        .map_err(|error| format!("Failed to store {account} in the keychain: {error}"))?;
+    Ok(Some(account))
+}
+
+/// Read the secret a saved reference points to. Missing or unreadable entries
+/// read as no secret, so the connection is restored and the user can enter the
+/// credential again.
+pub fn load(reference: Option<&str>) -> Option<String> {
+    entry(reference?).ok()?.get_password().ok()
+}
+
+/// Remove a stored secret. Failures are ignored: the entry is no longer
+/// referenced by any saved file.
+pub fn delete(account: &str) {
+    if let Ok(entry) = entry(account) {
+        let _ = entry.delete_credential();
+    }
+}
+
+fn entry(account: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, account)
+        .map_err(|error| format!("Failed to open keychain entry {account}: {error}"))
+}
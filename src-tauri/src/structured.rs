@@ -0,0 +1,157 @@
+//! Structured JSON output.
+//!
+//! Ollama, LM Studio and OpenAI-compatible servers are asked to constrain
+//! generation to JSON or a JSON Schema, and every backend is told the expected
+//! format in the system prompt. The final text is validated here in all cases:
+//! not every server enforces the schema, and the rest only follow the prompt.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Most schema violations listed in a validation error
+const MAX_REPORTED_ERRORS: usize = 5;
+
+/// Format the response must follow: `"json"` for any JSON value, or a JSON
+/// Schema object
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Value")]
+pub enum ResponseFormat {
+    Json,
+    Schema(Value),
+}
+
+impl TryFrom<Value> for ResponseFormat {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(format) if format.eq_ignore_ascii_case("json") => Ok(Self::Json),
+            Value::Object(_) => Ok(Self::Schema(value)),
+            _ => Err("response_format must be \"json\" or a JSON Schema object".to_string()),
+        }
+    }
+}
+
+impl ResponseFormat {
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            Self::Json => None,
+            Self::Schema(schema) => Some(schema),
+        }
+    }
+
+    /// Value of Ollama's `format` field
+    pub fn ollama_format(&self) -> Value {
+        match self {
+            Self::Json => json!("json"),
+            Self::Schema(schema) => schema.clone(),
+        }
+    }
+
+    /// OpenAI-style `response_format`; plain JSON mode uses `json_object`
+    pub fn openai_response_format(&self) -> Value {
+        match self {
+            Self::Json => json!({ "type": "json_object" }),
+            Self::Schema(schema) => json_schema_format(schema),
+        }
+    }
+
+    /// `response_format` that always carries a schema, for servers without a
+    /// plain JSON mode
+    pub fn json_schema_format(&self) -> Value {
+        match self {
+            Self::Json => json_schema_format(&json!({ "type": "object" })),
+            Self::Schema(schema) => json_schema_format(schema),
+        }
+    }
+
+    /// Instruction appended to the system prompt; OpenAI's JSON mode also
+    /// rejects requests that never mention JSON
+    pub fn instruction(&self) -> String {
+        match self {
+            Self::Json => {
+                "Respond only with valid JSON, without code fences or other text.".to_string()
+            }
+            Self::Schema(schema) => format!(
+                "Respond only with valid JSON matching this JSON Schema, without code fences or \
+                 other text:\n{schema}"
+            ),
+        }
+    }
+}
+
+fn json_schema_format(schema: &Value) -> Value {
+    json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": "response",
+            "schema": schema,
+        },
+    })
+}
+
+/// Append the format instruction to a system prompt
+pub fn with_instruction(system_prompt: Option<String>, format: &ResponseFormat) -> String {
+    match system_prompt.filter(|prompt| !prompt.trim().is_empty()) {
+        Some(prompt) => format!("{prompt}\n\n{}", format.instruction()),
+        None => format.instruction(),
+    }
+}
+
+/// Parses and validates responses against a [`ResponseFormat`]
+pub struct StructuredOutput {
+    validator: Option<jsonschema::Validator>,
+}
+
+impl StructuredOutput {
+    /// Compile the schema up front so an invalid one fails before the request
+    pub fn new(format: &ResponseFormat) -> Result<Self, String> {
+        let validator = format
+            .schema()
+            .map(jsonschema::validator_for)
+            .transpose()
+            .map_err(|e| format!("Invalid response schema: {e}"))?;
+        Ok(Self { validator })
+    }
+
+    /// Parse the response text as JSON and check it against the schema
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        let value: Value = serde_json::from_str(strip_code_fence(text))
+            .map_err(|e| format!("Response is not valid JSON: {e}"))?;
+
+        if let Some(validator) = &self.validator {
+            let errors: Vec<String> = validator
+                .iter_errors(&value)
+                .take(MAX_REPORTED_ERRORS)
+                .map(|error| {
+                    let path = error.instance_path.to_string();
+                    let path = if path.is_empty() { "/" } else { path.as_str() };
+                    format!("{path}: {error}")
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(format!(
+                    "Response does not match the schema: {}",
+                    errors.join("; ")
+                ));
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Models sometimes wrap JSON in a Markdown code fence despite instructions
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    let Some(inner) = text.strip_prefix("```") else {
+        return text;
+    };
+    let Some(inner) = inner.strip_suffix("```") else {
+        return text;
+    };
+    // Drop the info string, e.g. `json`
+    match inner.split_once('\n') {
+        Some((_, body)) => body.trim(),
+        None => inner.trim(),
+    }
+}
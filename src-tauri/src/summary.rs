@@ -0,0 +1,94 @@
+//! Rolling summaries of long conversations.
+//!
+//! Once the history after the stored summary passes the configured token
+//! threshold, the oldest turns are folded into the summary with a side request,
+//! which may go to a smaller model on another connection. Requests then send
+//! the summary as a pinned system message in place of the messages it covers.
+
+use crate::backend::{BackendChatRequest, ChatBackend};
+use crate::context::{estimate_message_tokens, group_turns, summary_message};
+use crate::models::{ConversationSummary, SummarizationConfig};
+
+/// Replace the messages covered by `summary` with the summary itself, first
+/// folding older turns into it when the rest of the history is over the
+/// threshold.
+///
+/// Returns whether the summary changed. When the summary request fails the
+/// existing summary is still applied and the error is returned, so callers can
+/// carry on with the request. Callers skip this for chats continuing a
+/// backend-side session, which only send the newest turn.
+pub async fn apply_rolling_summary(
+    summarizer: &dyn ChatBackend,
+    summarizer_model: &str,
+    config: &SummarizationConfig,
+    summary: &mut Option<ConversationSummary>,
+    request: &mut BackendChatRequest,
+) -> Result<bool, String> {
+    // A summary whose last message is gone no longer matches the history,
+    // e.g. after the conversation was edited or truncated
+    let covered = summary.as_ref().and_then(|summary| {
+        request
+            .messages
+            .iter()
+            .position(|message| message.id == summary.through_message_id)
+            .map(|index| index + 1)
+    });
+    if covered.is_none() {
+        *summary = None;
+    }
+    let covered = covered.unwrap_or(0);
+
+    let rest = &request.messages[covered..];
+    let rest_tokens: u32 = rest.iter().map(estimate_message_tokens).sum();
+    let mut fold = 0;
+    if rest_tokens > config.token_threshold {
+        // Fold whole turns, keeping at least `keep_recent_messages` unsummarised
+        let keep_from = rest
+            .len()
+            .saturating_sub(config.keep_recent_messages.max(1));
+        fold = group_turns(rest)
+            .iter()
+            .map(|turn| turn.end)
+            .take_while(|end| *end <= keep_from)
+            .last()
+            .unwrap_or(0);
+    }
+
+    let mut result = Ok(false);
+    if fold > 0 {
+        let previous = summary.as_ref().map(|summary| summary.content.as_str());
+        match summarizer
+            .summarize(summarizer_model, previous, &rest[..fold])
+            .await
+        {
+            Ok(content) if !content.trim().is_empty() => {
+                let summarized = summary
+                    .as_ref()
+                    .map_or(0, |summary| summary.summarized_message_count);
+                *summary = Some(ConversationSummary {
+                    content: content.trim().to_string(),
+                    through_message_id: rest[fold - 1].id.clone(),
+                    summarized_message_count: summarized + fold,
+                    model: summarizer_model.to_string(),
+                    updated_at: chrono::Utc::now(),
+                });
+                result = Ok(true);
+            }
+            Ok(_) => result = Err("The summary model returned an empty summary".to_string()),
+            Err(error) => result = Err(format!("Failed to update conversation summary: {error}")),
+        }
+    }
+
+    if let Some(summary) = summary.as_ref() {
+        let covered = if result == Ok(true) {
+            covered + fold
+        } else {
+            covered
+        };
+        request.messages.splice(
+            ..covered,
+            std::iter::once(summary_message(&summary.content)),
+        );
+    }
+    result
+}
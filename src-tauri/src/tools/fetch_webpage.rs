@@ -0,0 +1,121 @@
+use super::policy::{self, FetchPolicy};
+use super::readability::{self, Article};
+use super::{Tool, ToolContext};
+use crate::models::{ToolConfig, ToolResult};
+use async_trait::async_trait;
+use reqwest::Url;
+use serde_json::{json, Value};
+
+/// Built-in tool that fetches a web page for the model
+pub struct FetchWebpageTool;
+
+#[async_trait]
+impl Tool for FetchWebpageTool {
+    fn name(&self) -> &str {
+        "fetch_webpage"
+    }
+
+    fn description(&self) -> &str {
+        "Fetch a web page by URL and return its main content as Markdown, with title, byline and description."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "Absolute http(s) URL of the page to fetch",
+                },
+            },
+            "required": ["url"],
+        })
+    }
+
+    async fn execute(&self, arguments: &Value, context: &ToolContext) -> ToolResult {
+        let Some(url) = arguments.get("url").and_then(|v| v.as_str()) else {
+            return ToolResult::failure("Missing required argument: url");
+        };
+
+        match fetch_webpage(url, &context.config).await {
+            Ok(page) => {
+                let summary = page
+                    .get("url")
+                    .and_then(|v| v.as_str())
+                    .map(|url| format!("Fetched {url}"));
+                ToolResult {
+                    success: true,
+                    data: page,
+                    summary,
+                }
+            }
+            Err(error) => ToolResult::failure(error),
+        }
+    }
+}
+
+/// Maximum number of characters of page content returned to the caller
+const MAX_CONTENT_CHARS: usize = 40_000;
+
+/// Fetch a web page and return its main content as Markdown with page metadata.
+///
+/// The URL and every redirect are checked against the fetch policy built from
+/// `config`, and the body download stops at [`policy::MAX_BODY_BYTES`].
+pub async fn fetch_webpage(url: &str, config: &ToolConfig) -> Result<Value, String> {
+    let policy = FetchPolicy::from_config(config);
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    policy.check_url(&parsed)?;
+
+    let client = policy.client(std::time::Duration::from_secs(30))?;
+    let response = client
+        .get(parsed)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch URL: {}", policy::describe_error(&e)))?;
+
+    let status = response.status().as_u16();
+    let final_url = response.url().clone();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    policy::check_content_type(content_type.as_deref())?;
+
+    let (body, body_cut_off) = policy::read_body_limited(response, policy::MAX_BODY_BYTES).await?;
+    let body = String::from_utf8_lossy(&body);
+    let content_type = content_type.unwrap_or_else(|| "unknown".to_string());
+
+    let article = if content_type.contains("text/html") || content_type.contains("xhtml") {
+        readability::extract(&body, &final_url)
+    } else {
+        Article {
+            markdown: body.into_owned(),
+            ..Article::default()
+        }
+    };
+
+    let mut content = article.markdown;
+    let length = content.chars().count();
+    let mut truncated = readability::truncate(&mut content, MAX_CONTENT_CHARS);
+    if body_cut_off && !truncated {
+        content.push_str(&format!(
+            "\n\n[Content truncated: page exceeded the {} MB download limit]",
+            policy::MAX_BODY_BYTES / (1024 * 1024)
+        ));
+        truncated = true;
+    }
+
+    Ok(json!({
+        "url": final_url.as_str(),
+        "status": status,
+        "content_type": content_type,
+        "title": article.title,
+        "byline": article.byline,
+        "canonical_url": article.canonical_url,
+        "description": article.description,
+        "content": content,
+        "length": length,
+        "truncated": truncated,
+    }))
+}
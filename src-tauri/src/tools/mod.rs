@@ -0,0 +1,266 @@
+mod fetch_webpage;
+mod policy;
+mod readability;
+mod web_search;
+
+pub use fetch_webpage::fetch_webpage;
+pub use web_search::web_search;
+
+use crate::backend::{BackendChatRequest, BackendChatResponse, ChatBackend, ChunkCallback};
+use crate::models::{Message, MessageRole, ToolCall, ToolCallStatus, ToolConfig, ToolResult};
+use async_trait::async_trait;
+use fetch_webpage::FetchWebpageTool;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use web_search::WebSearchTool;
+
+/// Model-facing description of a tool, with JSON Schema parameters.
+///
+/// Backend adapters translate this into their native tool format.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+/// Settings and paths available to tools while they run
+#[derive(Debug, Clone, Default)]
+pub struct ToolContext {
+    pub config: ToolConfig,
+    /// App cache directory, used for tool caches such as search results
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// A tool the model can call during a chat
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON Schema describing the tool's arguments
+    fn parameters(&self) -> Value;
+
+    /// Whether the tool can be offered with the current settings
+    fn is_available(&self, _config: &ToolConfig) -> bool {
+        true
+    }
+
+    /// Run the tool; failures are reported through `ToolResult.success`
+    async fn execute(&self, arguments: &Value, context: &ToolContext) -> ToolResult;
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: self.parameters(),
+        }
+    }
+}
+
+/// Callback invoked whenever a tool call changes status
+pub type ToolCallCallback<'a> = dyn FnMut(&ToolCall) + Send + 'a;
+
+/// Registry of tools available to models, in registration order
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn with_builtin_tools() -> Self {
+        let mut registry = Self { tools: Vec::new() };
+        registry.register(Arc::new(FetchWebpageTool));
+        registry.register(Arc::new(WebSearchTool));
+        registry
+    }
+
+    /// Add a tool, replacing any existing tool with the same name
+    pub fn register(&mut self, tool: Arc<dyn Tool>) {
+        self.tools.retain(|existing| existing.name() != tool.name());
+        self.tools.push(tool);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.iter().find(|tool| tool.name() == name).cloned()
+    }
+
+    /// Definitions of every registered tool
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|tool| tool.definition()).collect()
+    }
+
+    /// Definitions of the tools enabled for a conversation.
+    ///
+    /// `None` enables every registered tool; unknown names are ignored. Nothing
+    /// is offered while tool calling is off in `config`, and tools unavailable
+    /// under it, such as web search when it is off, are left out.
+    pub fn enabled_definitions(
+        &self,
+        enabled: Option<&[String]>,
+        config: &ToolConfig,
+    ) -> Vec<ToolDefinition> {
+        if !config.enabled {
+            return Vec::new();
+        }
+        self.tools
+            .iter()
+            .filter(|tool| tool.is_available(config))
+            .filter(|tool| enabled.is_none_or(|names| names.iter().any(|name| name == tool.name())))
+            .map(|tool| tool.definition())
+            .collect()
+    }
+
+    /// Run a single tool call. Only tools offered in `offered` may run, so a
+    /// model cannot reach a tool the conversation has disabled.
+    async fn execute(
+        &self,
+        call: &ToolCall,
+        offered: &[ToolDefinition],
+        context: &ToolContext,
+    ) -> ToolResult {
+        if !offered.iter().any(|tool| tool.name == call.tool_name) {
+            return ToolResult::failure(format!("Tool not available: {}", call.tool_name));
+        }
+        match self.get(&call.tool_name) {
+            Some(tool) => tool.execute(&call.arguments, context).await,
+            None => ToolResult::failure(format!("Unknown tool: {}", call.tool_name)),
+        }
+    }
+
+    /// Chat with a backend, executing requested tool calls until the model gives
+    /// a final answer or `context.config.max_tool_calls` is reached.
+    ///
+    /// Each round appends the assistant's tool-call message and one `tool` role
+    /// message per result, so backends translate the history into their own
+    /// tool-calling format. The returned response carries every executed call.
+    pub async fn chat_with_tools(
+        &self,
+        backend: &dyn ChatBackend,
+        mut request: BackendChatRequest,
+        streaming: bool,
+        context: &ToolContext,
+        on_chunk: &mut ChunkCallback<'_>,
+        on_tool_call: &mut ToolCallCallback<'_>,
+    ) -> Result<BackendChatResponse, String> {
+        let mut executed: Vec<ToolCall> = Vec::new();
+        // Token counts and cost of the earlier rounds
+        let mut usage = BackendChatResponse::default();
+
+        loop {
+            if executed.len() as u32 >= context.config.max_tool_calls {
+                request.tools.clear();
+            }
+
+            let mut response = if streaming {
+                backend.chat_stream(&request, on_chunk).await?
+            } else {
+                backend.chat(&request).await?
+            };
+
+            if response.tool_calls.is_empty() || request.tools.is_empty() {
+                executed.append(&mut response.tool_calls);
+                response.tool_calls = executed;
+                response.add_usage(&usage);
+                return Ok(response);
+            }
+            usage.add_usage(&response);
+
+            let remaining = (context.config.max_tool_calls as usize).saturating_sub(executed.len());
+            let requested = std::mem::take(&mut response.tool_calls);
+            let mut round = Vec::new();
+
+            for mut call in requested.into_iter().take(remaining) {
+                call.status = ToolCallStatus::Running;
+                on_tool_call(&call);
+
+                let started = std::time::Instant::now();
+                let result = self.execute(&call, &request.tools, context).await;
+                if result.success {
+                    call.status = ToolCallStatus::Completed;
+                } else {
+                    call.status = ToolCallStatus::Failed;
+                    call.error_message = result.summary.clone();
+                }
+                call.result = Some(result);
+                call.execution_time_ms = Some(started.elapsed().as_millis() as u64);
+                on_tool_call(&call);
+
+                round.push(call);
+            }
+
+            request.messages.push(tool_message(
+                MessageRole::Assistant,
+                response.content,
+                round.clone(),
+            ));
+            for call in &round {
+                request.messages.push(tool_message(
+                    MessageRole::Tool,
+                    tool_result_content(call),
+                    vec![call.clone()],
+                ));
+            }
+
+            executed.extend(round);
+        }
+    }
+}
+
+/// Content of the `tool` role message that reports a call's result
+fn tool_result_content(call: &ToolCall) -> String {
+    match (&call.error_message, &call.result) {
+        (Some(error), _) => format!("Error: {error}"),
+        (None, Some(result)) => result.data.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Rebuild the tool-calling rounds of stored history.
+///
+/// The app stores a tool-using answer as one assistant message carrying every
+/// executed call. Backends expect the calls, then one `tool` message per
+/// result, then the answer, so such messages are split back into that shape.
+/// Calls that never ran are dropped. The split messages get ids derived from
+/// the original, so they stay stable across requests.
+pub fn expand_tool_history(messages: Vec<Message>) -> Vec<Message> {
+    let mut expanded = Vec::with_capacity(messages.len());
+    for mut message in messages {
+        if message.role != MessageRole::Assistant || message.tool_calls.is_empty() {
+            expanded.push(message);
+            continue;
+        }
+
+        let executed: Vec<ToolCall> = std::mem::take(&mut message.tool_calls)
+            .into_iter()
+            .filter(|call| call.result.is_some() || call.error_message.is_some())
+            .collect();
+        if !executed.is_empty() {
+            let mut calls = tool_message(MessageRole::Assistant, String::new(), executed.clone());
+            calls.id = format!("{}-calls", message.id);
+            expanded.push(calls);
+            for call in executed {
+                let mut result =
+                    tool_message(MessageRole::Tool, tool_result_content(&call), Vec::new());
+                result.id = format!("{}-{}", message.id, call.id);
+                result.tool_calls.push(call);
+                expanded.push(result);
+            }
+        }
+        expanded.push(message);
+    }
+    expanded
+}
+
+/// Build a message for the tool-calling history.
+///
+/// Assistant messages carry the calls the model requested; `tool` messages
+/// carry the single call whose result they hold.
+fn tool_message(role: MessageRole, content: String, tool_calls: Vec<ToolCall>) -> Message {
+    Message {
+        tool_calls,
+        ..Message::new(role, content)
+    }
+}
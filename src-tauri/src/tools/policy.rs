@@ -0,0 +1,271 @@
+//! Safety policy for fetching URLs chosen by the model or the user.
+//!
+//! Only public http(s) hosts are reachable: addresses are checked after DNS
+//! resolution and again on every redirect, bodies are cut off at a size limit
+//! and only text-like content types are accepted.
+
+use crate::models::ToolConfig;
+use futures::StreamExt;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{redirect, Response, Url};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Maximum number of response body bytes read before the download is cut off
+pub const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+
+const MAX_REDIRECTS: usize = 5;
+
+const ALLOWED_SCHEMES: &[&str] = &["http", "https"];
+
+const ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "text/html",
+    "application/xhtml+xml",
+    "text/plain",
+    "text/markdown",
+    "text/csv",
+    "text/xml",
+    "application/xml",
+    "application/json",
+    "application/ld+json",
+    "application/rss+xml",
+    "application/atom+xml",
+];
+
+/// Which URLs a fetch may reach, built from the user's `ToolConfig`
+#[derive(Debug, Clone, Default)]
+pub struct FetchPolicy {
+    allowed_domains: Vec<String>,
+    blocked_domains: Vec<String>,
+}
+
+impl FetchPolicy {
+    pub fn from_config(config: &ToolConfig) -> Self {
+        Self {
+            allowed_domains: normalize_domains(&config.allowed_domains),
+            blocked_domains: normalize_domains(&config.blocked_domains),
+        }
+    }
+
+    /// Check the scheme, host and domain lists of a URL before requesting it.
+    ///
+    /// Hostnames are checked against private addresses at DNS resolution time by
+    /// the client returned from [`FetchPolicy::client`].
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        if !ALLOWED_SCHEMES.contains(&url.scheme()) {
+            return Err(format!("URL scheme '{}' is not allowed", url.scheme()));
+        }
+
+        let host = url
+            .host_str()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| format!("URL has no host: {}", url))?
+            .to_lowercase();
+
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if !is_public_ip(ip) {
+                return Err(format!("Access to non-public address {} is blocked", ip));
+            }
+        }
+
+        if self
+            .blocked_domains
+            .iter()
+            .any(|domain| domain_matches(&host, domain))
+        {
+            return Err(format!("Domain {} is blocked by the tool settings", host));
+        }
+        if !self.allowed_domains.is_empty()
+            && !self
+                .allowed_domains
+                .iter()
+                .any(|domain| domain_matches(&host, domain))
+        {
+            return Err(format!(
+                "Domain {} is not in the allowed domains list",
+                host
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// HTTP client that enforces this policy on every redirect and refuses to
+    /// connect to hosts resolving to private, loopback or link-local addresses
+    pub fn client(&self, timeout: Duration) -> Result<reqwest::Client, String> {
+        let policy = self.clone();
+        let redirect_policy = redirect::Policy::custom(move |attempt| {
+            // `previous` includes the original URL
+            if attempt.previous().len() > MAX_REDIRECTS {
+                return attempt.error(format!("Stopped after {} redirects", MAX_REDIRECTS));
+            }
+            match policy.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(error) => attempt.error(error),
+            }
+        });
+
+        reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent("Mozilla/5.0 (compatible; PrivateChatHub/0.1)")
+            .redirect(redirect_policy)
+            .dns_resolver(PublicOnlyResolver)
+            // A proxy would resolve hosts itself and bypass the address check
+            .no_proxy()
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Reject content types that are not text-like. A missing header is allowed.
+pub fn check_content_type(content_type: Option<&str>) -> Result<(), String> {
+    let Some(content_type) = content_type else {
+        return Ok(());
+    };
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+
+    if mime.is_empty() || ALLOWED_CONTENT_TYPES.contains(&mime.as_str()) {
+        Ok(())
+    } else {
+        Err(format!("Content type {} is not supported", mime))
+    }
+}
+
+/// Read a response body, stopping after `max_bytes`.
+///
+/// Returns the body and whether it was cut off.
+pub async fn read_body_limited(
+    response: Response,
+    max_bytes: usize,
+) -> Result<(Vec<u8>, bool), String> {
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to read response body: {}", e))?;
+        if body.len() + chunk.len() > max_bytes {
+            body.extend_from_slice(&chunk[..max_bytes - body.len()]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok((body, false))
+}
+
+/// Format an error with its source chain, so DNS and redirect policy
+/// rejections wrapped by reqwest stay visible
+pub fn describe_error(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        if !message.contains(&cause_message) {
+            message.push_str(": ");
+            message.push_str(&cause_message);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+/// DNS resolver that fails when a host resolves to any non-public address
+struct PublicOnlyResolver;
+
+impl Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+
+            if addrs.is_empty() {
+                return Err(format!("No addresses found for {}", host).into());
+            }
+            if let Some(blocked) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!(
+                    "Access to {} is blocked: it resolves to non-public address {}",
+                    host,
+                    blocked.ip()
+                )
+                .into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn normalize_domains(domains: &[String]) -> Vec<String> {
+    domains
+        .iter()
+        .map(|domain| {
+            domain
+                .trim()
+                .trim_start_matches("*.")
+                .trim_start_matches('.')
+                .trim_end_matches('.')
+                .to_lowercase()
+        })
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Whether `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network", carrier-grade NAT, IETF protocol assignments,
+        // benchmarking and reserved ranges
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (b == 18 || b == 19))
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(ipv4) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(ipv4);
+    }
+    let segments = ip.segments();
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // IPv4-compatible, unique local, link-local, site-local,
+        // documentation and NAT64 ranges
+        || segments[..6].iter().all(|segment| *segment == 0)
+        || (segments[0] & 0xfe00) == 0xfc00
+        || (segments[0] & 0xffc0) == 0xfe80
+        || (segments[0] & 0xffc0) == 0xfec0
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        || (segments[0] == 0x0064 && segments[1] == 0xff9b))
+}
@@ -0,0 +1,618 @@
+//! Readability-style main content extraction and HTML to Markdown conversion
+//! for fetched web pages.
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+
+/// Elements that never contain article content
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "svg", "canvas", "form", "button",
+    "input", "select", "textarea", "nav", "footer", "aside", "dialog", "menu",
+];
+
+/// Class/id fragments that mark navigation, banners and other page chrome
+const BOILERPLATE_HINTS: &[&str] = &[
+    "cookie",
+    "consent",
+    "banner",
+    "navbar",
+    "nav-",
+    "menu",
+    "sidebar",
+    "footer",
+    "breadcrumb",
+    "share",
+    "social",
+    "advert",
+    "promo",
+    "newsletter",
+    "subscribe",
+    "popup",
+    "modal",
+    "related",
+    "comment",
+    "skip-link",
+];
+
+/// Class/id fragments that mark likely content containers
+const CONTENT_HINTS: &[&str] = &[
+    "article", "content", "entry", "main", "post", "story", "body",
+];
+
+/// Metadata and Markdown body extracted from an HTML page
+#[derive(Debug, Clone, Default)]
+pub struct Article {
+    pub title: Option<String>,
+    pub byline: Option<String>,
+    pub canonical_url: Option<String>,
+    pub description: Option<String>,
+    pub markdown: String,
+}
+
+/// Extract the main content of a page as Markdown, along with its metadata.
+///
+/// Relative links and images are resolved against `page_url`.
+pub fn extract(html: &str, page_url: &Url) -> Article {
+    let document = Html::parse_document(html);
+    let renderer = MarkdownRenderer { base_url: page_url };
+
+    let root = find_content_root(&document);
+    let markdown = normalize_markdown(&renderer.render_blocks(root));
+
+    Article {
+        title: meta_content(&document, "meta[property='og:title']")
+            .or_else(|| select_text(&document, "title"))
+            .or_else(|| select_text(&document, "h1")),
+        byline: meta_content(&document, "meta[name='author']")
+            .or_else(|| meta_content(&document, "meta[property='article:author']"))
+            .or_else(|| select_text(&document, "[rel='author']"))
+            .or_else(|| select_text(&document, ".byline")),
+        canonical_url: select_first(&document, "link[rel='canonical']")
+            .and_then(|link| link.value().attr("href"))
+            .and_then(|href| page_url.join(href.trim()).ok())
+            .map(|url| url.to_string()),
+        description: meta_content(&document, "meta[name='description']")
+            .or_else(|| meta_content(&document, "meta[property='og:description']")),
+        markdown,
+    }
+}
+
+/// Cut `content` to at most `max_chars` characters, preferring a paragraph
+/// boundary, and append a truncation marker. Returns whether it was cut.
+pub fn truncate(content: &mut String, max_chars: usize) -> bool {
+    let total = content.chars().count();
+    if total <= max_chars {
+        return false;
+    }
+
+    let cut = content
+        .char_indices()
+        .nth(max_chars)
+        .map(|(index, _)| index)
+        .unwrap_or(content.len());
+    let cut = match content[..cut].rfind("\n\n") {
+        Some(boundary) if boundary > cut / 2 => boundary,
+        _ => cut,
+    };
+
+    content.truncate(cut);
+    let shown = content.chars().count();
+    content.push_str(&format!(
+        "\n\n[Content truncated: showing {} of {} characters]",
+        shown, total
+    ));
+    true
+}
+
+fn select_first<'a>(document: &'a Html, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    document.select(&selector).next()
+}
+
+fn select_text(document: &Html, selector: &str) -> Option<String> {
+    select_first(document, selector)
+        .and_then(|element| non_empty(&collapse_whitespace(&element.text().collect::<String>())))
+}
+
+fn meta_content(document: &Html, selector: &str) -> Option<String> {
+    select_first(document, selector)
+        .and_then(|meta| meta.value().attr("content"))
+        .and_then(|content| non_empty(&collapse_whitespace(content)))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Pick the element most likely to hold the article body
+fn find_content_root(document: &Html) -> ElementRef<'_> {
+    for selector in ["article", "main, [role='main']"] {
+        let Ok(selector) = Selector::parse(selector) else {
+            continue;
+        };
+        let best = document
+            .select(&selector)
+            .map(|element| (text_length(element), element))
+            .max_by_key(|(length, _)| *length);
+        if let Some((length, element)) = best {
+            if length >= 200 {
+                return element;
+            }
+        }
+    }
+
+    score_candidates(document)
+        .or_else(|| select_first(document, "body"))
+        .unwrap_or_else(|| document.root_element())
+}
+
+/// Score paragraph containers by text amount and comma count, readability style
+fn score_candidates(document: &Html) -> Option<ElementRef<'_>> {
+    let selector = Selector::parse("p, pre, td, blockquote").ok()?;
+    let mut scores = HashMap::new();
+
+    for paragraph in document.select(&selector) {
+        if has_skipped_ancestor(paragraph) {
+            continue;
+        }
+        let text = paragraph.text().collect::<String>();
+        let length = text.trim().chars().count();
+        if length < 25 {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+        let Some(parent) = paragraph.parent().and_then(ElementRef::wrap) else {
+            continue;
+        };
+        *scores.entry(parent.id()).or_insert(0.0) += score;
+        if let Some(grandparent) = parent.parent().and_then(ElementRef::wrap) {
+            *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+            let bonus = if matches_hints(element, CONTENT_HINTS) {
+                1.25
+            } else {
+                1.0
+            };
+            Some((score * bonus * (1.0 - link_density(element)), element))
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, element)| element)
+}
+
+fn text_length(element: ElementRef<'_>) -> usize {
+    element.text().map(|text| text.trim().chars().count()).sum()
+}
+
+fn link_density(element: ElementRef<'_>) -> f64 {
+    let total = text_length(element);
+    if total == 0 {
+        return 1.0;
+    }
+    let Ok(selector) = Selector::parse("a") else {
+        return 0.0;
+    };
+    let linked: usize = element.select(&selector).map(text_length).sum();
+    linked as f64 / total as f64
+}
+
+fn has_skipped_ancestor(element: ElementRef<'_>) -> bool {
+    element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(is_boilerplate)
+}
+
+fn matches_hints(element: ElementRef<'_>, hints: &[&str]) -> bool {
+    let value = element.value();
+    let marker = format!(
+        "{} {}",
+        value.id().unwrap_or(""),
+        value.attr("class").unwrap_or("")
+    )
+    .to_lowercase();
+    hints.iter().any(|hint| marker.contains(hint))
+}
+
+/// Whether an element is page chrome rather than content
+fn is_boilerplate(element: ElementRef<'_>) -> bool {
+    let value = element.value();
+    let name = value.name();
+
+    if SKIPPED_TAGS.contains(&name) || value.attr("hidden").is_some() {
+        return true;
+    }
+    if value.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if matches!(
+        value.attr("role"),
+        Some("navigation" | "banner" | "contentinfo" | "dialog")
+    ) {
+        return true;
+    }
+    // Site headers are chrome, but article headers hold the headline
+    if name == "header"
+        && !element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| matches!(ancestor.value().name(), "article" | "main"))
+    {
+        return true;
+    }
+
+    matches!(name, "div" | "section" | "span" | "ul" | "p" | "header")
+        && matches_hints(element, BOILERPLATE_HINTS)
+        && !matches_hints(element, CONTENT_HINTS)
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "address"
+            | "article"
+            | "blockquote"
+            | "dd"
+            | "details"
+            | "div"
+            | "dl"
+            | "dt"
+            | "figcaption"
+            | "figure"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "li"
+            | "main"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "summary"
+            | "table"
+            | "ul"
+    )
+}
+
+struct MarkdownRenderer<'a> {
+    base_url: &'a Url,
+}
+
+impl MarkdownRenderer<'_> {
+    /// Render a container's children, wrapping runs of inline content as paragraphs
+    fn render_blocks(&self, element: ElementRef<'_>) -> String {
+        let mut output = String::new();
+        let mut inline = String::new();
+
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(text),
+                Node::Element(value) if is_block(value.name()) => {
+                    push_paragraph(&mut output, &inline);
+                    inline.clear();
+                    if let Some(child) = ElementRef::wrap(child) {
+                        output.push_str(&self.render_element(child));
+                    }
+                }
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        inline.push_str(&self.render_element(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        push_paragraph(&mut output, &inline);
+
+        output
+    }
+
+    fn render_inline(&self, element: ElementRef<'_>) -> String {
+        let mut output = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => output.push_str(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        output.push_str(&self.render_element(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn render_element(&self, element: ElementRef<'_>) -> String {
+        if is_boilerplate(element) {
+            return String::new();
+        }
+
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = clean_inline(&self.render_inline(element)).replace('\n', " ");
+                if text.is_empty() {
+                    String::new()
+                } else {
+                    format!("\n\n{} {}\n\n", "#".repeat(level), text)
+                }
+            }
+            "p" | "summary" | "figcaption" | "dt" | "address" => {
+                let mut output = String::new();
+                push_paragraph(&mut output, &self.render_inline(element));
+                output
+            }
+            "br" => "\n".to_string(),
+            "hr" => "\n\n---\n\n".to_string(),
+            "strong" | "b" => wrap_inline(&self.render_inline(element), "**"),
+            "em" | "i" => wrap_inline(&self.render_inline(element), "*"),
+            "del" | "s" | "strike" => wrap_inline(&self.render_inline(element), "~~"),
+            "code" | "kbd" | "samp" => {
+                let code = element.text().collect::<String>();
+                if code.trim().is_empty() {
+                    String::new()
+                } else {
+                    let fence = if code.contains('`') { "``" } else { "`" };
+                    format!("{fence}{}{fence}", code.trim())
+                }
+            }
+            "pre" => self.render_code_block(element),
+            "a" => self.render_link(element),
+            "img" => self.render_image(element),
+            "ul" | "ol" => self.render_list(element, name == "ol"),
+            "blockquote" => {
+                let inner = normalize_markdown(&self.render_blocks(element));
+                if inner.is_empty() {
+                    return String::new();
+                }
+                let quoted = inner
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            ">".to_string()
+                        } else {
+                            format!("> {line}")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("\n\n{quoted}\n\n")
+            }
+            "table" => self.render_table(element),
+            _ if is_block(name) => format!("\n\n{}\n\n", self.render_blocks(element)),
+            _ => self.render_inline(element),
+        }
+    }
+
+    fn render_code_block(&self, element: ElementRef<'_>) -> String {
+        let code = element.text().collect::<String>();
+        let code = code.trim_matches('\n').trim_end();
+        if code.is_empty() {
+            return String::new();
+        }
+
+        let language = std::iter::once(element)
+            .chain(
+                element
+                    .child_elements()
+                    .filter(|child| child.value().name() == "code"),
+            )
+            .flat_map(|el| el.value().classes())
+            .find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+            })
+            .unwrap_or("");
+        let fence = if code.contains("```") { "~~~" } else { "```" };
+
+        format!("\n\n{fence}{language}\n{code}\n{fence}\n\n")
+    }
+
+    fn render_link(&self, element: ElementRef<'_>) -> String {
+        let text = clean_inline(&self.render_inline(element)).replace('\n', " ");
+        let href = element
+            .value()
+            .attr("href")
+            .map(str::trim)
+            .filter(|href| !href.is_empty() && !href.starts_with('#'))
+            .filter(|href| !href.to_lowercase().starts_with("javascript:"))
+            .and_then(|href| self.base_url.join(href).ok());
+
+        match href {
+            Some(url) if !text.is_empty() => format!("[{}]({})", text, url),
+            _ => text,
+        }
+    }
+
+    fn render_image(&self, element: ElementRef<'_>) -> String {
+        let value = element.value();
+        let src = value
+            .attr("src")
+            .or_else(|| value.attr("data-src"))
+            .map(str::trim)
+            .filter(|src| !src.is_empty() && !src.starts_with("data:"))
+            .and_then(|src| self.base_url.join(src).ok());
+        let alt = collapse_whitespace(value.attr("alt").unwrap_or(""));
+
+        match src {
+            Some(url) => format!("![{}]({})", alt.trim(), url),
+            None => String::new(),
+        }
+    }
+
+    fn render_list(&self, element: ElementRef<'_>, ordered: bool) -> String {
+        let start = element
+            .value()
+            .attr("start")
+            .and_then(|start| start.parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let items = element
+            .child_elements()
+            .filter(|child| child.value().name() == "li" && !is_boilerplate(*child))
+            .map(|item| normalize_markdown(&self.render_blocks(item)))
+            .filter(|item| !item.is_empty())
+            .enumerate()
+            .map(|(index, item)| {
+                let marker = if ordered {
+                    format!("{}. ", start + index)
+                } else {
+                    "- ".to_string()
+                };
+                let indent = " ".repeat(marker.len());
+                let lines = item.lines().collect::<Vec<_>>();
+                lines
+                    .iter()
+                    .enumerate()
+                    // Keep nested lists tight against their parent item
+                    .filter(|(line_index, line)| {
+                        !(line.is_empty()
+                            && lines
+                                .get(line_index + 1)
+                                .is_some_and(|next| is_list_line(next)))
+                    })
+                    .map(|(line_index, line)| match (line_index, line.is_empty()) {
+                        (0, _) => format!("{marker}{line}"),
+                        (_, true) => String::new(),
+                        _ => format!("{indent}{line}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            String::new()
+        } else {
+            format!("\n\n{}\n\n", items.join("\n"))
+        }
+    }
+
+    fn render_table(&self, element: ElementRef<'_>) -> String {
+        let Ok(row_selector) = Selector::parse("tr") else {
+            return String::new();
+        };
+        let rows = element
+            .select(&row_selector)
+            .map(|row| {
+                row.child_elements()
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .map(|cell| {
+                        clean_inline(&self.render_inline(cell))
+                            .replace('\n', " ")
+                            .replace('|', "\\|")
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        // Single-column tables are usually layout, not data
+        if columns < 2 {
+            return format!("\n\n{}\n\n", self.render_blocks(element));
+        }
+
+        let format_row = |row: &[String]| {
+            let cells = (0..columns)
+                .map(|index| row.get(index).map(String::as_str).unwrap_or(""))
+                .collect::<Vec<_>>();
+            format!("| {} |", cells.join(" | "))
+        };
+
+        let mut lines = vec![format_row(&rows[0])];
+        lines.push(format!("|{}", " --- |".repeat(columns)));
+        lines.extend(rows[1..].iter().map(|row| format_row(row)));
+
+        format!("\n\n{}\n\n", lines.join("\n"))
+    }
+}
+
+fn is_list_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("- ")
+        || line.split_once(". ").is_some_and(|(number, _)| {
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+fn push_paragraph(output: &mut String, inline: &str) {
+    let text = clean_inline(inline);
+    if !text.is_empty() {
+        output.push_str("\n\n");
+        output.push_str(&text);
+        output.push_str("\n\n");
+    }
+}
+
+fn wrap_inline(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    // Keep surrounding spaces outside the markers so emphasis still parses
+    let leading = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Collapse whitespace in inline content while keeping explicit line breaks
+fn clean_inline(text: &str) -> String {
+    text.split('\n')
+        .map(collapse_whitespace)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Trim trailing spaces and collapse runs of blank lines outside code fences
+fn normalize_markdown(markdown: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_end();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            lines.push(line);
+            continue;
+        }
+        if trimmed.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(trimmed);
+    }
+
+    lines.join("\n").trim().to_string()
+}
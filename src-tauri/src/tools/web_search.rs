@@ -0,0 +1,340 @@
+use super::{Tool, ToolContext};
+use crate::models::{SearchProvider, ToolConfig, ToolResult};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+const JINA_SEARCH_URL: &str = "https://s.jina.ai/";
+
+/// How long cached search results are reused
+const CACHE_TTL_HOURS: i64 = 24;
+
+const MAX_SNIPPET_CHARS: usize = 300;
+
+/// Built-in tool that searches the web with the configured provider
+pub struct WebSearchTool;
+
+#[async_trait]
+impl Tool for WebSearchTool {
+    fn name(&self) -> &str {
+        "web_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the web and return ranked results with title, URL and snippet. Use fetch_webpage to read a result in full."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Search query",
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": "Maximum number of results to return",
+                },
+            },
+            "required": ["query"],
+        })
+    }
+
+    fn is_available(&self, config: &ToolConfig) -> bool {
+        config.web_search_enabled
+    }
+
+    async fn execute(&self, arguments: &Value, context: &ToolContext) -> ToolResult {
+        let Some(query) = arguments.get("query").and_then(|v| v.as_str()) else {
+            return ToolResult::failure("Missing required argument: query");
+        };
+        let max_results = arguments
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .map(|v| v.min(u32::MAX as u64) as u32);
+
+        match web_search(query, max_results, context).await {
+            Ok(results) => {
+                let count = results
+                    .get("results")
+                    .and_then(|v| v.as_array())
+                    .map_or(0, Vec::len);
+                ToolResult {
+                    success: true,
+                    summary: Some(format!("Found {} results for \"{}\"", count, query.trim())),
+                    data: results,
+                }
+            }
+            Err(error) => ToolResult::failure(error),
+        }
+    }
+}
+
+/// A single ranked search hit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub rank: usize,
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSearch {
+    cached_at: DateTime<Utc>,
+    results: Vec<SearchResult>,
+}
+
+#[derive(Deserialize)]
+struct JinaSearchResponse {
+    #[serde(default)]
+    data: Vec<JinaSearchItem>,
+}
+
+#[derive(Deserialize)]
+struct JinaSearchItem {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    description: Option<String>,
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    url: String,
+    content: Option<String>,
+}
+
+/// Search the web with the provider from the tool settings.
+///
+/// Returns at most `max_search_results` results, reusing results cached on
+/// disk for the same query when `cache_search_results` is set.
+pub async fn web_search(
+    query: &str,
+    max_results: Option<u32>,
+    context: &ToolContext,
+) -> Result<Value, String> {
+    let config = &context.config;
+    if !config.web_search_enabled {
+        return Err("Web search is disabled in the tool settings".to_string());
+    }
+
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+    let limit = max_results
+        .unwrap_or(config.max_search_results)
+        .min(config.max_search_results)
+        .max(1) as usize;
+
+    let cache_file = context
+        .cache_dir
+        .as_deref()
+        .filter(|_| config.cache_search_results)
+        .map(|dir| cache_path(dir, config, query));
+
+    let cached = match &cache_file {
+        Some(path) => read_cache(path).await,
+        None => None,
+    };
+    let from_cache = cached.is_some();
+    let mut results = match cached {
+        Some(results) => results,
+        None => {
+            let results = match config.search_provider {
+                SearchProvider::Jina => search_jina(query, config).await?,
+                SearchProvider::Searxng => search_searxng(query, config).await?,
+            };
+            if let Some(path) = &cache_file {
+                // A failed cache write only costs a repeat request later
+                let _ = write_cache(path, &results).await;
+            }
+            results
+        }
+    };
+    results.truncate(limit);
+
+    Ok(json!({
+        "query": query,
+        "provider": config.search_provider,
+        "results": results,
+        "cached": from_cache,
+    }))
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .user_agent("Mozilla/5.0 (compatible; PrivateChatHub/0.1)")
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn endpoint(config: &ToolConfig) -> Option<&str> {
+    config
+        .search_endpoint
+        .as_deref()
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+}
+
+async fn search_jina(query: &str, config: &ToolConfig) -> Result<Vec<SearchResult>, String> {
+    let url = Url::parse_with_params(endpoint(config).unwrap_or(JINA_SEARCH_URL), &[("q", query)])
+        .map_err(|e| format!("Invalid search endpoint: {}", e))?;
+
+    let mut request = http_client()?
+        .get(url)
+        .header("Accept", "application/json")
+        .header("X-Respond-With", "no-content");
+    if let Some(api_key) = config
+        .jina_api_key
+        .as_deref()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+    {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Search request failed: {}", e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(
+            "Jina rejected the search request; check the Jina API key in the tool settings"
+                .to_string(),
+        );
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Search failed ({}): {}", status, body));
+    }
+
+    let body: JinaSearchResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse search results: {}", e))?;
+
+    Ok(rank_results(body.data.into_iter().map(|item| {
+        let snippet = item.description.or(item.content).unwrap_or_default();
+        (item.title, item.url, snippet)
+    })))
+}
+
+async fn search_searxng(query: &str, config: &ToolConfig) -> Result<Vec<SearchResult>, String> {
+    let base_url = endpoint(config).ok_or_else(|| {
+        "Set the SearXNG URL in the tool settings to use it for web search".to_string()
+    })?;
+    let url = Url::parse_with_params(
+        &format!("{}/search", base_url.trim_end_matches('/')),
+        &[("q", query), ("format", "json")],
+    )
+    .map_err(|e| format!("Invalid search endpoint: {}", e))?;
+
+    let response = http_client()?
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Search request failed: {}", e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN {
+        return Err("SearXNG refused the request; enable the json format under search.formats in its settings.yml".to_string());
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Search failed ({}): {}", status, body));
+    }
+
+    let body: SearxngResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse search results: {}", e))?;
+
+    Ok(rank_results(body.results.into_iter().map(|result| {
+        (result.title, result.url, result.content.unwrap_or_default())
+    })))
+}
+
+/// Number results in provider order, dropping entries without a URL
+fn rank_results(items: impl Iterator<Item = (String, String, String)>) -> Vec<SearchResult> {
+    items
+        .filter(|(_, url, _)| !url.trim().is_empty())
+        .enumerate()
+        .map(|(index, (title, url, snippet))| SearchResult {
+            rank: index + 1,
+            title: collapse_whitespace(&title),
+            url: url.trim().to_string(),
+            snippet: collapse_whitespace(&snippet)
+                .chars()
+                .take(MAX_SNIPPET_CHARS)
+                .collect(),
+        })
+        .collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cache file for a search; the name is a SHA-256 digest, so it stays the same
+/// across builds and Rust versions
+fn cache_path(cache_dir: &Path, config: &ToolConfig, query: &str) -> PathBuf {
+    let provider = serde_json::to_string(&config.search_provider).unwrap_or_default();
+    let query = query.to_lowercase();
+    let mut hasher = Sha256::new();
+    for part in [
+        provider.as_str(),
+        endpoint(config).unwrap_or(""),
+        query.as_str(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    cache_dir
+        .join("web_search")
+        .join(format!("{:x}.json", hasher.finalize()))
+}
+
+async fn read_cache(path: &Path) -> Option<Vec<SearchResult>> {
+    let contents = tokio::fs::read(path).await.ok()?;
+    let cached: CachedSearch = serde_json::from_slice(&contents).ok()?;
+    (Utc::now() - cached.cached_at < chrono::Duration::hours(CACHE_TTL_HOURS))
+        .then_some(cached.results)
+}
+
+async fn write_cache(path: &Path, results: &[SearchResult]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    let cached = CachedSearch {
+        cached_at: Utc::now(),
+        results: results.to_vec(),
+    };
+    let contents = serde_json::to_vec(&cached).map_err(|e| e.to_string())?;
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,451 @@
+//! Token and cost accounting for chat responses.
+//!
+//! Every completed or cancelled response is appended to a JSON Lines ledger in
+//! the app data directory; budgets and model prices are kept in a small
+//! settings file next to it. Costs come from the backend when it reports one,
+//! otherwise from the per-1k token prices of the cloud provider's models.
+//! Monthly budgets block further requests to priced models once the month's
+//! spend reaches the limit.
+
+use crate::models::CloudModel;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Budget and prices; also the whole ledger in earlier versions
+const SETTINGS_FILE: &str = "usage.json";
+
+/// One usage record per line, only ever appended to
+const LEDGER_FILE: &str = "usage.jsonl";
+
+/// Report key for usage outside any project
+const NO_PROJECT: &str = "none";
+
+/// Price of a cloud model in USD per 1000 tokens
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelPrice {
+    input_per_1k: f64,
+    output_per_1k: f64,
+}
+
+/// Monthly spend limits in USD; unset limits never block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UsageBudget {
+    /// Limit on the total spend across all providers
+    pub monthly_limit: Option<f64>,
+    /// Limits keyed by backend, e.g. `openai` or `anthropic`
+    pub provider_limits: HashMap<String, f64>,
+}
+
+/// One completed chat response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub connection_id: String,
+    /// Backend key of the connection, e.g. `ollama` or `openai`
+    pub provider: String,
+    pub model: String,
+    pub project_id: Option<String>,
+    pub conversation_id: Option<String>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost: f64,
+    /// The generation was stopped early; token counts are estimates
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Aggregated usage for one model, provider, project or day
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    pub key: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageBucket {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += u64::from(record.prompt_tokens);
+        self.completion_tokens += u64::from(record.completion_tokens);
+        self.cost += record.cost;
+    }
+}
+
+/// Spend against a monthly limit
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    /// `total` for the overall limit, otherwise the provider key
+    pub scope: String,
+    pub limit: f64,
+    pub spent: f64,
+    pub exceeded: bool,
+}
+
+/// Result of the `get_usage_report` command
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub total: UsageBucket,
+    pub by_model: Vec<UsageBucket>,
+    pub by_provider: Vec<UsageBucket>,
+    pub by_project: Vec<UsageBucket>,
+    pub by_day: Vec<UsageBucket>,
+    pub budget: UsageBudget,
+    /// Spend in the current calendar month against each configured limit
+    pub budget_status: Vec<BudgetStatus>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct UsageSettings {
+    budget: UsageBudget,
+    /// Prices keyed by connection id, then model id
+    prices: HashMap<String, HashMap<String, ModelPrice>>,
+}
+
+struct LoadedUsage {
+    data_dir: PathBuf,
+    settings: UsageSettings,
+    records: Vec<UsageRecord>,
+}
+
+impl LoadedUsage {
+    fn load(data_dir: &Path) -> Result<Self, String> {
+        let settings_path = data_dir.join(SETTINGS_FILE);
+        let settings: UsageSettings = match fs::read_to_string(&settings_path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(settings) => settings,
+                // A damaged file must not block every chat: keep it for
+                // inspection and start over
+                Err(_) => {
+                    let corrupt_path = settings_path.with_extension("json.corrupt");
+                    fs::rename(&settings_path, &corrupt_path).map_err(|error| {
+                        format!("Failed to move aside {}: {error}", settings_path.display())
+                    })?;
+                    UsageSettings::default()
+                }
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => UsageSettings::default(),
+            Err(error) => {
+                return Err(format!(
+                    "Failed to read {}: {error}",
+                    settings_path.display()
+                ))
+            }
+        };
+
+        let ledger_path = data_dir.join(LEDGER_FILE);
+        let records: Vec<UsageRecord> = match fs::read_to_string(&ledger_path) {
+            // A line cut short by a crash is skipped rather than failing the load
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(format!("Failed to read {}: {error}", ledger_path.display())),
+        };
+
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            settings,
+            records,
+        })
+    }
+
+    fn save_settings(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.data_dir)
+            .map_err(|error| format!("Failed to create {}: {error}", self.data_dir.display()))?;
+        let path = self.data_dir.join(SETTINGS_FILE);
+        let temp_path = path.with_extension("tmp");
+        let bytes = serde_json::to_vec(&self.settings)
+            .map_err(|error| format!("Failed to serialize usage settings: {error}"))?;
+        fs::write(&temp_path, bytes)
+            .map_err(|error| format!("Failed to write {}: {error}", temp_path.display()))?;
+        fs::rename(&temp_path, &path)
+            .map_err(|error| format!("Failed to finalize {}: {error}", path.display()))
+    }
+
+    /// Add one record to the end of the ledger file
+    fn append(&self, record: &UsageRecord) -> Result<(), String> {
+        fs::create_dir_all(&self.data_dir)
+            .map_err(|error| format!("Failed to create {}: {error}", self.data_dir.display()))?;
+        let path = self.data_dir.join(LEDGER_FILE);
+        let mut line = serde_json::to_string(record)
+            .map_err(|error| format!("Failed to serialize usage record: {error}"))?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+    }
+
+    fn price(&self, connection_id: &str, model: &str) -> Option<ModelPrice> {
+        self.settings
+            .prices
+            .get(connection_id)
+            .and_then(|prices| prices.get(model))
+            .copied()
+    }
+
+    /// Spend since the start of the current month, optionally for one provider
+    fn month_spend(&self, provider: Option<&str>) -> f64 {
+        let month_start = month_start(Utc::now());
+        self.records
+            .iter()
+            .filter(|record| record.timestamp >= month_start)
+            .filter(|record| provider.is_none_or(|provider| record.provider == provider))
+            .map(|record| record.cost)
+            .sum()
+    }
+
+    fn budget_status(&self) -> Vec<BudgetStatus> {
+        let budget = &self.settings.budget;
+        let overall = budget
+            .monthly_limit
+            .map(|limit| ("total".to_string(), limit, None));
+        let providers = budget
+            .provider_limits
+            .iter()
+            .map(|(provider, limit)| (provider.clone(), *limit, Some(provider.as_str())));
+
+        let mut statuses: Vec<BudgetStatus> = overall
+            .into_iter()
+            .chain(providers)
+            .map(|(scope, limit, provider)| {
+                let spent = self.month_spend(provider);
+                BudgetStatus {
+                    scope,
+                    limit,
+                    spent,
+                    exceeded: spent >= limit,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.scope.cmp(&b.scope));
+        statuses
+    }
+}
+
+/// Records usage per response and enforces monthly budgets
+pub struct UsageTracker {
+    /// Ledger, budget and prices, loaded from the app data directory on first use
+    loaded: Mutex<Option<LoadedUsage>>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self {
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// Replace the model prices of a cloud provider connection
+    pub fn set_prices(
+        &self,
+        data_dir: &Path,
+        connection_id: &str,
+        models: &[CloudModel],
+    ) -> Result<(), String> {
+        let prices = models
+            .iter()
+            .filter(|model| model.input_cost_per_1k > 0.0 || model.output_cost_per_1k > 0.0)
+            .map(|model| {
+                (
+                    model.id.clone(),
+                    ModelPrice {
+                        input_per_1k: model.input_cost_per_1k,
+                        output_per_1k: model.output_cost_per_1k,
+                    },
+                )
+            })
+            .collect();
+
+        self.with_usage(data_dir, |loaded| {
+            loaded
+                .settings
+                .prices
+                .insert(connection_id.to_string(), prices);
+            loaded.save_settings()
+        })
+    }
+
+    pub fn remove_prices(&self, data_dir: &Path, connection_id: &str) -> Result<(), String> {
+        self.with_usage(data_dir, |loaded| {
+            if loaded.settings.prices.remove(connection_id).is_some() {
+                loaded.save_settings()?;
+            }
+            Ok(())
+        })
+    }
+
+    fn with_usage<T>(
+        &self,
+        data_dir: &Path,
+        f: impl FnOnce(&mut LoadedUsage) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut loaded = self.loaded.lock().map_err(|e| e.to_string())?;
+        if loaded.is_none() {
+            *loaded = Some(LoadedUsage::load(data_dir)?);
+        }
+
+        f(loaded.as_mut().expect("usage loaded above"))
+    }
+
+    /// Fail when a request to a priced model would go over a monthly budget.
+    ///
+    /// Models without a configured price, such as local ones, are never blocked.
+    pub fn check_budget(
+        &self,
+        data_dir: &Path,
+        connection_id: &str,
+        provider: &str,
+        model: &str,
+    ) -> Result<(), String> {
+        self.with_usage(data_dir, |loaded| {
+            if loaded.price(connection_id, model).is_none() {
+                return Ok(());
+            }
+
+            let budget = &loaded.settings.budget;
+            if let Some(limit) = budget.monthly_limit {
+                let spent = loaded.month_spend(None);
+                if spent >= limit {
+                    return Err(format!(
+                        "Monthly budget of ${limit:.2} reached (${spent:.2} spent). Raise the limit in the usage settings to keep using paid models."
+                    ));
+                }
+            }
+            if let Some(limit) = budget.provider_limits.get(provider).copied() {
+                let spent = loaded.month_spend(Some(provider));
+                if spent >= limit {
+                    return Err(format!(
+                        "Monthly {provider} budget of ${limit:.2} reached (${spent:.2} spent). Raise the limit in the usage settings to keep using this provider."
+                    ));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Record a completed or cancelled response and return its cost, if known.
+    ///
+    /// `reported_cost` is used as-is when the backend computed one itself.
+    pub fn record(
+        &self,
+        data_dir: &Path,
+        mut record: UsageRecord,
+        prompt_tokens: Option<u32>,
+        completion_tokens: Option<u32>,
+        reported_cost: Option<f64>,
+    ) -> Result<Option<f64>, String> {
+        self.with_usage(data_dir, |loaded| {
+            let cost = match reported_cost {
+                Some(cost) => Some(cost),
+                None => loaded
+                    .price(&record.connection_id, &record.model)
+                    .map(|price| {
+                        f64::from(prompt_tokens.unwrap_or(0)) / 1000.0 * price.input_per_1k
+                            + f64::from(completion_tokens.unwrap_or(0)) / 1000.0
+                                * price.output_per_1k
+                    }),
+            };
+
+            record.prompt_tokens = prompt_tokens.unwrap_or(0);
+            record.completion_tokens = completion_tokens.unwrap_or(0);
+            record.cost = cost.unwrap_or(0.0);
+
+            loaded.append(&record)?;
+            loaded.records.push(record);
+            Ok(cost)
+        })
+    }
+
+    pub fn set_budget(&self, data_dir: &Path, budget: UsageBudget) -> Result<(), String> {
+        self.with_usage(data_dir, |loaded| {
+            loaded.settings.budget = budget;
+            loaded.save_settings()
+        })
+    }
+
+    /// Aggregate recorded usage between `since` and `until` (inclusive)
+    pub fn report(
+        &self,
+        data_dir: &Path,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<UsageReport, String> {
+        self.with_usage(data_dir, |loaded| {
+            let mut total = UsageBucket {
+                key: "total".to_string(),
+                ..UsageBucket::default()
+            };
+            let mut by_model = BTreeMap::new();
+            let mut by_provider = BTreeMap::new();
+            let mut by_project = BTreeMap::new();
+            let mut by_day = BTreeMap::new();
+
+            let records = loaded.records.iter().filter(|record| {
+                since.is_none_or(|since| record.timestamp >= since)
+                    && until.is_none_or(|until| record.timestamp <= until)
+            });
+            for record in records {
+                total.add(record);
+                for (buckets, key) in [
+                    (&mut by_model, record.model.clone()),
+                    (&mut by_provider, record.provider.clone()),
+                    (
+                        &mut by_project,
+                        record
+                            .project_id
+                            .clone()
+                            .unwrap_or_else(|| NO_PROJECT.to_string()),
+                    ),
+                    (&mut by_day, record.timestamp.format("%Y-%m-%d").to_string()),
+                ] {
+                    buckets
+                        .entry(key.clone())
+                        .or_insert_with(|| UsageBucket {
+                            key,
+                            ..UsageBucket::default()
+                        })
+                        .add(record);
+                }
+            }
+
+            Ok(UsageReport {
+                since,
+                until,
+                total,
+                by_model: by_model.into_values().collect(),
+                by_provider: by_provider.into_values().collect(),
+                by_project: by_project.into_values().collect(),
+                by_day: by_day.into_values().collect(),
+                budget: loaded.settings.budget.clone(),
+                budget_status: loaded.budget_status(),
+            })
+        })
+    }
+}
+
+fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
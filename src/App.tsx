@@ -11,7 +11,7 @@ import './App.css';
 function App() {
   const [isHydrated, setIsHydrated] = useState(false);
   const { currentView, sidebarOpen } = useUIStore();
-  const { connections, testConnection, checkStatus } = useConnectionStore();
+  const { testConnection, checkStatus } = useConnectionStore();
   const { fetchModels } = useModelStore();
   const { settings } = useSettingsStore();
 
@@ -30,10 +30,11 @@ function App() {
     }
   }, [settings.theme]);
 
-  // Auto-connect on startup
+  // Auto-connect on startup; testing refreshes the connection list, so read it once
   useEffect(() => {
     if (!isHydrated) return;
 
+    const { connections } = useConnectionStore.getState();
     const defaultConn = connections.find((c) => c.isDefault) || connections[0];
     if (defaultConn) {
       testConnection(
@@ -43,12 +44,13 @@ function App() {
         defaultConn.backend,
         defaultConn.username,
         defaultConn.password,
-        defaultConn.apiToken
+        defaultConn.apiToken,
+        defaultConn.id
       ).then((ok) => {
         if (ok) fetchModels();
       });
     }
-  }, [isHydrated, connections, testConnection, fetchModels]);
+  }, [isHydrated, testConnection, fetchModels]);
 
   // Periodic health check
   useEffect(() => {
@@ -18,7 +18,7 @@ import {
 } from 'lucide-react';
 import { useChatStore, useModelStore, useConnectionStore, useSettingsStore } from '../../stores';
 import { formatTime, getBackendLabel, getDisplayModelName, supportsVision, supportsTools } from '../../utils/format';
-import type { BackendType, Message, ModelParameters } from '../../types';
+import type { BackendType, Message, ModelParameters, ToolDefinition } from '../../types';
 import { PARAMETER_PRESETS } from '../../types';
 import TextareaAutosize from 'react-textarea-autosize';
 
@@ -33,6 +33,9 @@ export function ChatView() {
     updateConversationModel,
     deleteConversation,
     toggleToolCalling,
+    availableTools,
+    fetchTools,
+    toggleTool,
   } = useChatStore();
 
   const { models } = useModelStore();
@@ -52,6 +55,10 @@ export function ChatView() {
     ? [activeConv.modelName, ...visibleModelNames]
     : visibleModelNames;
 
+  useEffect(() => {
+    if (modelSupportsTools && availableTools.length === 0) void fetchTools();
+  }, [modelSupportsTools, availableTools.length, fetchTools]);
+
   if (!activeConv) {
     return <EmptyState />;
   }
@@ -75,6 +82,9 @@ export function ChatView() {
         supportsTools={modelSupportsTools}
         toolCallingEnabled={activeConv.toolCallingEnabled}
         onToggleToolCalling={() => toggleToolCalling(activeConv.id)}
+        tools={availableTools}
+        enabledTools={activeConv.enabledTools}
+        onToggleTool={(toolName) => toggleTool(activeConv.id, toolName)}
       />
     </div>
   );
@@ -178,6 +188,7 @@ function ConversationSettings({
   const { updateConversationParams, updateSystemPrompt } = useChatStore();
   const [params, setParams] = useState(conversation.parameters);
   const [prompt, setPrompt] = useState(conversation.systemPrompt || '');
+  const [stopText, setStopText] = useState((conversation.parameters.stop ?? []).join(', '));
 
   return (
     <div className="conv-settings-panel">
@@ -247,6 +258,58 @@ function ConversationSettings({
           }}
         />
       </div>
+
+      <div className="settings-section">
+        <label>Context Window</label>
+        <input
+          type="number"
+          className="input"
+          value={params.numCtx || ''}
+          placeholder="Model default"
+          onChange={(e) => {
+            const val = e.target.value ? parseInt(e.target.value) : undefined;
+            const newParams = { ...params, numCtx: val };
+            setParams(newParams);
+            updateConversationParams(conversation.id, newParams);
+          }}
+        />
+      </div>
+
+      <div className="settings-section">
+        <label>Seed</label>
+        <input
+          type="number"
+          className="input"
+          value={params.seed ?? ''}
+          placeholder="Random"
+          onChange={(e) => {
+            const val = e.target.value ? parseInt(e.target.value) : undefined;
+            const newParams = { ...params, seed: val };
+            setParams(newParams);
+            updateConversationParams(conversation.id, newParams);
+          }}
+        />
+      </div>
+
+      <div className="settings-section">
+        <label>Stop Sequences</label>
+        <input
+          type="text"
+          className="input"
+          value={stopText}
+          placeholder="Comma-separated"
+          onChange={(e) => setStopText(e.target.value)}
+          onBlur={() => {
+            const stop = stopText
+              .split(',')
+              .map((sequence) => sequence.trim())
+              .filter(Boolean);
+            const newParams = { ...params, stop: stop.length ? stop : undefined };
+            setParams(newParams);
+            updateConversationParams(conversation.id, newParams);
+          }}
+        />
+      </div>
     </div>
   );
 }
@@ -380,6 +443,9 @@ function ChatInput({
   supportsTools,
   toolCallingEnabled,
   onToggleToolCalling,
+  tools = [],
+  enabledTools,
+  onToggleTool,
 }: {
   onSend: (content: string, attachments?: File[]) => Promise<void>;
   isDisabled: boolean;
@@ -390,6 +456,10 @@ function ChatInput({
   supportsTools?: boolean;
   toolCallingEnabled?: boolean;
   onToggleToolCalling?: () => void;
+  tools?: ToolDefinition[];
+  /** Unset enables every tool */
+  enabledTools?: string[];
+  onToggleTool?: (toolName: string) => void;
 }) {
   const [input, setInput] = useState('');
   const [attachments, setAttachments] = useState<File[]>([]);
@@ -437,6 +507,24 @@ function ChatInput({
             ))}
           </div>
         )}
+        {supportsTools && toolCallingEnabled && tools.length > 0 && (
+          <div className="tool-toggles">
+            {tools.map((tool) => {
+              const enabled = !enabledTools || enabledTools.includes(tool.name);
+              return (
+                <button
+                  key={tool.name}
+                  className={`tool-toggle ${enabled ? 'active' : ''}`}
+                  onClick={() => onToggleTool?.(tool.name)}
+                  title={tool.description}
+                  disabled={isDisabled}
+                >
+                  {tool.name}
+                </button>
+              );
+            })}
+          </div>
+        )}
         <div className="chat-input-row">
           <div className="chat-input-actions">
             {supportsVision && (
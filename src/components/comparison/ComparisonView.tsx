@@ -54,6 +54,7 @@ export function ComparisonView() {
         ],
         systemPrompt: null,
         parameters: null,
+        connectionId: models.find((model) => model.name === model1)?.connection_id ?? activeConnection?.id ?? null,
       });
       setResult(comparison);
     } catch (err) {
@@ -162,11 +162,11 @@ function ModelDetailModal({ model, onClose }: { model: OllamaModel; onClose: ()
   const [loading, setLoading] = useState(true);
 
   useEffect(() => {
-    invoke<any>('show_model', { modelName: model.name })
+    invoke<any>('show_model', { modelName: model.name, connectionId: model.connection_id ?? null })
       .then(setDetails)
       .catch(() => {})
       .finally(() => setLoading(false));
-  }, [model.name]);
+  }, [model.name, model.connection_id]);
 
   const capabilities = getModelCapabilities(model.name);
 
@@ -18,7 +18,10 @@ import {
   Radio,
   Copy,
   ListFilter,
+  Cloud,
+  Trash2,
 } from 'lucide-react';
+import { v4 as uuidv4 } from 'uuid';
 import {
   useSettingsStore,
   useConnectionStore,
@@ -27,7 +30,7 @@ import {
   useModelStore,
   useFolderSyncStore,
 } from '../../stores';
-import type { AppSettings, BackendType } from '../../types';
+import type { AppSettings, BackendType, CloudModel, CloudProvider } from '../../types';
 
 const BACKEND_DETAILS: Record<
   BackendType,
@@ -67,6 +70,7 @@ export function SettingsView() {
     <div className="settings-view">
       <h2>Settings</h2>
       <ConnectionSettings />
+      <CloudProviderSettings />
       <OpencodeModelPreferencesSettings />
       <ThemeSettings />
       <ToolSettings />
@@ -79,11 +83,11 @@ export function SettingsView() {
 }
 
 function ConnectionSettings() {
-  const { connections, isConnected, isConnecting, connectionError, testConnection, updateConnection } =
+  const { connections, isConnected, isConnecting, connectionError, testConnection, setDefaultConnection } =
     useConnectionStore();
   const { fetchModels } = useModelStore();
 
-  const defaultConn = connections[0] || {
+  const defaultConn = connections.find((c) => c.isDefault) || connections[0] || {
     backend: 'ollama' as const,
     host: 'localhost',
     port: BACKEND_DETAILS.ollama.defaultPort,
@@ -105,6 +109,8 @@ function ConnectionSettings() {
 
   const handleTest = async () => {
     setTestResult(null);
+    // Each provider edits its own registered connection
+    const connectionId = connections.find((c) => c.backend === backend)?.id;
     const ok = await testConnection(
       host,
       port,
@@ -113,22 +119,13 @@ function ConnectionSettings() {
       backend === 'opencode' ? username || undefined : undefined,
       backend === 'opencode' ? password || undefined : undefined,
       backend === 'lmstudio' ? apiToken || undefined : undefined,
+      connectionId,
     );
     setTestResult(ok ? 'success' : 'error');
     if (ok) {
-      const conn = connections[0];
-      if (conn) {
-        updateConnection(conn.id, {
-          backend,
-          host,
-          port,
-          useHttps,
-          username: backend === 'opencode' ? username || undefined : undefined,
-          password: backend === 'opencode' ? password || undefined : undefined,
-          apiToken: backend === 'lmstudio' ? apiToken || undefined : undefined,
-          name: backendDetail.connectionName,
-          lastConnectedAt: new Date().toISOString(),
-        });
+      const tested = useConnectionStore.getState().activeConnection;
+      if (tested) {
+        await setDefaultConnection(tested.id);
       }
       await fetchModels();
     }
@@ -295,6 +292,198 @@ function ConnectionSettings() {
   );
 }
 
+const CLOUD_PROVIDER_LABELS: Record<CloudProvider['providerType'], string> = {
+  openai: 'OpenAI-compatible',
+  anthropic: 'Anthropic',
+  google: 'Google Gemini',
+};
+
+/** One model per line: `id, context length, input $/1k tokens, output $/1k tokens` */
+function formatCloudModels(models: CloudModel[]): string {
+  return models
+    .map((m) => [m.id, m.maxContext, m.inputCostPer1k, m.outputCostPer1k].join(', '))
+    .join('\n');
+}
+
+function parseCloudModels(text: string, previous: CloudModel[]): CloudModel[] {
+  return text
+    .split('\n')
+    .map((line) => line.split(',').map((part) => part.trim()))
+    .filter(([id]) => id)
+    .map(([id, maxContext, inputCost, outputCost]) => {
+      const existing = previous.find((m) => m.id === id);
+      return {
+        id,
+        name: existing?.name ?? id,
+        maxContext: parseInt(maxContext, 10) || 0,
+        supportsVision: existing?.supportsVision ?? false,
+        supportsTools: existing?.supportsTools ?? false,
+        inputCostPer1k: parseFloat(inputCost) || 0,
+        outputCostPer1k: parseFloat(outputCost) || 0,
+      };
+    });
+}
+
+function CloudProviderSettings() {
+  const { cloudProviders, saveCloudProvider, removeCloudProvider } = useConnectionStore();
+  const { fetchModels } = useModelStore();
+
+  const [editing, setEditing] = useState<CloudProvider | null>(null);
+  const [name, setName] = useState('');
+  const [providerType, setProviderType] = useState<CloudProvider['providerType']>('openai');
+  const [baseUrl, setBaseUrl] = useState('');
+  const [apiKey, setApiKey] = useState('');
+  const [modelsText, setModelsText] = useState('');
+  const [isSaving, setIsSaving] = useState(false);
+  const [status, setStatus] = useState<{ type: 'success' | 'error'; message: string } | null>(null);
+
+  const resetForm = (provider: CloudProvider | null) => {
+    setEditing(provider);
+    setName(provider?.name ?? '');
+    setProviderType(provider?.providerType ?? 'openai');
+    setBaseUrl(provider?.baseUrl ?? '');
+    setApiKey(provider?.apiKey ?? '');
+    setModelsText(formatCloudModels(provider?.models ?? []));
+  };
+
+  const handleSave = async () => {
+    if (!name.trim()) return;
+    setIsSaving(true);
+    setStatus(null);
+    try {
+      await saveCloudProvider({
+        id: editing?.id ?? uuidv4(),
+        name: name.trim(),
+        providerType,
+        apiKey: apiKey || undefined,
+        baseUrl: baseUrl.trim() || undefined,
+        enabled: editing?.enabled ?? true,
+        models: parseCloudModels(modelsText, editing?.models ?? []),
+      });
+      resetForm(null);
+      setStatus({ type: 'success', message: 'Provider saved' });
+      await fetchModels();
+    } catch (err) {
+      setStatus({ type: 'error', message: String(err) });
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleRemove = async (id: string) => {
+    setStatus(null);
+    try {
+      await removeCloudProvider(id);
+      if (editing?.id === id) resetForm(null);
+      await fetchModels();
+    } catch (err) {
+      setStatus({ type: 'error', message: String(err) });
+    }
+  };
+
+  return (
+    <div className="settings-card">
+      <div className="settings-card-header">
+        <Cloud size={20} />
+        <h3>Cloud Providers</h3>
+      </div>
+      <div className="settings-form">
+        <p style={{ fontSize: '12px', color: 'var(--text-secondary)', marginBottom: '12px' }}>
+          Providers are saved on this device and reconnected on startup. Model prices are used for usage costs and budgets.
+        </p>
+
+        {cloudProviders.map((provider) => (
+          <div key={provider.id} className="form-row">
+            <span className="form-label">
+              {provider.name} ({CLOUD_PROVIDER_LABELS[provider.providerType]}, {provider.models.length} models)
+            </span>
+            <button className="btn btn-secondary" onClick={() => resetForm(provider)}>
+              Edit
+            </button>
+            <button className="btn btn-ghost btn-xs" onClick={() => handleRemove(provider.id)} title="Remove provider">
+              <Trash2 size={14} />
+            </button>
+          </div>
+        ))}
+
+        <div className="form-row">
+          <div className="form-group">
+            <label>Name</label>
+            <input
+              type="text"
+              className="input"
+              value={name}
+              onChange={(e) => setName(e.target.value)}
+              placeholder="OpenRouter"
+            />
+          </div>
+          <div className="form-group">
+            <label>Type</label>
+            <select
+              className="input"
+              value={providerType}
+              onChange={(e) => setProviderType(e.target.value as CloudProvider['providerType'])}
+            >
+              {Object.entries(CLOUD_PROVIDER_LABELS).map(([value, label]) => (
+                <option key={value} value={value}>{label}</option>
+              ))}
+            </select>
+          </div>
+        </div>
+
+        <div className="form-row">
+          <div className="form-group">
+            <label>Base URL (optional)</label>
+            <input
+              type="text"
+              className="input"
+              value={baseUrl}
+              onChange={(e) => setBaseUrl(e.target.value)}
+              placeholder="Defaults to the provider's public API"
+            />
+          </div>
+          <div className="form-group">
+            <label>API Key</label>
+            <input
+              type="password"
+              className="input"
+              value={apiKey}
+              onChange={(e) => setApiKey(e.target.value)}
+            />
+          </div>
+        </div>
+
+        <div className="form-group">
+          <label>Models (one per line: id, context length, input $/1k, output $/1k)</label>
+          <textarea
+            className="input"
+            rows={4}
+            value={modelsText}
+            onChange={(e) => setModelsText(e.target.value)}
+            placeholder="gpt-4o-mini, 128000, 0.00015, 0.0006"
+          />
+        </div>
+
+        <div className="form-actions">
+          <button className="btn btn-primary" onClick={handleSave} disabled={isSaving || !name.trim()}>
+            {isSaving ? <><Loader2 size={16} className="spin" /> Saving...</> : editing ? 'Update Provider' : 'Add Provider'}
+          </button>
+          {editing && (
+            <button className="btn btn-secondary" onClick={() => resetForm(null)}>
+              Cancel
+            </button>
+          )}
+          {status && (
+            <span className={`test-result ${status.type}`}>
+              {status.type === 'success' ? <CheckCircle size={16} /> : <XCircle size={16} />} {status.message}
+            </span>
+          )}
+        </div>
+      </div>
+    </div>
+  );
+}
+
 function OpencodeModelPreferencesSettings() {
   const { activeConnection } = useConnectionStore();
   const { models } = useModelStore();
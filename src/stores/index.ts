@@ -1,18 +1,29 @@
 import { create } from 'zustand';
 import { v4 as uuidv4 } from 'uuid';
 import type {
+  CloudProvider,
   Conversation,
+  ConversationSummary,
   Message,
   ModelParameters,
   OllamaModel,
+  ModelCreateRequest,
+  RunningModel,
   Connection,
+  ConnectionStatus,
   Project,
   AppSettings,
   FolderSyncConfig,
   FolderSyncSnapshot,
   FolderSyncStatus,
   SyncConfig,
+  BackendSession,
   BackendType,
+  PermissionRequest,
+  PermissionResponse,
+  ToolCall,
+  ToolDefinition,
+  ToolResult,
   View,
 } from '../types';
 import { invoke } from '@tauri-apps/api/core';
@@ -57,10 +68,44 @@ type PersistedFolderSyncState = NonNullable<PersistedState['persistence']>['fold
 type ChatStreamChunkEvent = {
   requestId: string;
   content: string;
+  reasoning?: string | null;
+  toolCall?: BackendToolCall | null;
   done: boolean;
+  promptEvalCount?: number;
   evalCount?: number;
   totalDuration?: number;
+  cost?: number;
   error?: string;
+  cancelled?: boolean;
+  partialContent?: string;
+};
+
+/** Tool call as serialized by the backend */
+type BackendToolCall = {
+  id: string;
+  tool_name: string;
+  arguments: Record<string, unknown>;
+  status: ToolCall['status'];
+  result?: ToolResult | null;
+  error_message?: string | null;
+  execution_time_ms?: number | null;
+};
+
+/** Message as serialized by the backend */
+type BackendMessage = {
+  id: string;
+  role: Message['role'];
+  content: string;
+  timestamp: string;
+  model_name?: string | null;
+  is_error: boolean;
+  token_count?: number | null;
+  prompt_token_count?: number | null;
+  cost?: number | null;
+  tool_calls: BackendToolCall[];
+  status: Message['status'];
+  status_message?: string | null;
+  reasoning?: string | null;
 };
 
 const appStore = new LazyStore('app-state.json');
@@ -70,6 +115,7 @@ let hasHydrated = false;
 let subscriptionsInitialized = false;
 let persistTimer: ReturnType<typeof setTimeout> | null = null;
 let streamListenerInitialized = false;
+let runningModelsListenerInitialized = false;
 
 const activeStreams = new Map<string, { conversationId: string; assistantMessageId: string; modelName: string }>();
 const streamsWithChunks = new Set<string>();
@@ -81,6 +127,54 @@ function normalizeMessage(message: Message, fallbackBackend?: BackendType): Mess
   };
 }
 
+/** Insert or replace a streamed tool call, keeping the order calls started in */
+function upsertToolCall(toolCalls: ToolCall[], call: BackendToolCall): ToolCall[] {
+  const next: ToolCall = {
+    id: call.id,
+    toolName: call.tool_name,
+    arguments: call.arguments,
+    status: call.status,
+    result: call.result ?? undefined,
+    errorMessage: call.error_message ?? undefined,
+    executionTimeMs: call.execution_time_ms ?? undefined,
+  };
+  return toolCalls.some((existing) => existing.id === call.id)
+    ? toolCalls.map((existing) => (existing.id === call.id ? next : existing))
+    : [...toolCalls, next];
+}
+
+function toBackendToolCall(call: ToolCall): BackendToolCall {
+  return {
+    id: call.id,
+    tool_name: call.toolName,
+    arguments: call.arguments,
+    status: call.status,
+    result: call.result ?? null,
+    error_message: call.errorMessage ?? null,
+    execution_time_ms: call.executionTimeMs ?? null,
+  };
+}
+
+function fromBackendMessage(message: BackendMessage, backendType: BackendType): Message {
+  return {
+    id: message.id,
+    role: message.role,
+    content: message.content,
+    timestamp: message.timestamp,
+    modelName: message.model_name ?? undefined,
+    backendType,
+    isError: message.is_error,
+    tokenCount: message.token_count ?? undefined,
+    promptTokenCount: message.prompt_token_count ?? undefined,
+    cost: message.cost ?? undefined,
+    attachments: [],
+    toolCalls: message.tool_calls.reduce<ToolCall[]>(upsertToolCall, []),
+    status: message.status,
+    statusMessage: message.status_message ?? undefined,
+    reasoning: message.reasoning ?? undefined,
+  };
+}
+
 function normalizeConversation(conversation: Conversation, defaultBackend: BackendType, restoredFromFolder = false): Conversation {
   const backendType = conversation.backendType ?? defaultBackend;
   return {
@@ -252,6 +346,13 @@ const initPersistenceSubscriptions = () => {
   useChatStore.subscribe(() => schedulePersist());
   useProjectStore.subscribe(() => schedulePersist());
   useSettingsStore.subscribe(() => schedulePersist());
+  useSettingsStore.subscribe((state, previous) => {
+    if (state.settings.toolConfig !== previous.settings.toolConfig) {
+      void invoke('update_tool_config', { config: state.settings.toolConfig }).catch((error) => {
+        console.error('Failed to update tool config', error);
+      });
+    }
+  });
   useUIStore.subscribe(() => schedulePersist());
 };
 
@@ -264,7 +365,7 @@ const initStreamingListener = () => {
     const stream = activeStreams.get(payload.requestId);
     if (!stream) return;
 
-    if (payload.content) {
+    if (payload.content || payload.reasoning || payload.toolCall) {
       streamsWithChunks.add(payload.requestId);
       useChatStore.setState((state) => ({
         conversations: state.conversations.map((conversation) => {
@@ -277,6 +378,12 @@ const initStreamingListener = () => {
                 ? {
                     ...message,
                     content: `${message.content}${payload.content}`,
+                    reasoning: payload.reasoning
+                      ? `${message.reasoning ?? ''}${payload.reasoning}`
+                      : message.reasoning,
+                    toolCalls: payload.toolCall
+                      ? upsertToolCall(message.toolCalls, payload.toolCall)
+                      : message.toolCalls,
                     status: 'sending',
                   }
                 : message
@@ -299,6 +406,8 @@ const initStreamingListener = () => {
                     ...message,
                     modelName: stream.modelName,
                     tokenCount: payload.evalCount,
+                    promptTokenCount: payload.promptEvalCount,
+                    cost: payload.cost,
                     status: payload.error ? 'failed' : 'sent',
                     isError: Boolean(payload.error),
                     content: payload.error ? `Error: ${payload.error}` : message.content,
@@ -317,6 +426,45 @@ const initStreamingListener = () => {
   });
 };
 
+const initRunningModelsListener = () => {
+  if (runningModelsListenerInitialized) return;
+  runningModelsListenerInitialized = true;
+
+  void listen<RunningModel[]>('running_models_changed', (event) => {
+    useModelStore.setState({ runningModels: event.payload });
+  });
+};
+
+/** Rust `ModelParameters` field names */
+function toBackendParameters(parameters: ModelParameters) {
+  return {
+    temperature: parameters.temperature,
+    top_k: parameters.topK ?? null,
+    top_p: parameters.topP ?? null,
+    max_tokens: parameters.maxTokens ?? null,
+    num_ctx: parameters.numCtx ?? null,
+    seed: parameters.seed ?? null,
+    repeat_penalty: parameters.repeatPenalty ?? null,
+    min_p: parameters.minP ?? null,
+    stop: parameters.stop?.length ? parameters.stop : null,
+    mirostat: parameters.mirostat ?? null,
+    num_gpu: parameters.numGpu ?? null,
+  };
+}
+
+/** Connection serving a model in the merged model list */
+function modelConnectionId(modelName: string): string | null {
+  return useModelStore.getState().models.find((model) => model.name === modelName)?.connection_id ?? null;
+}
+
+function sameEndpoint(a: Connection, b: Connection): boolean {
+  return a.backend === b.backend && a.host === b.host && a.port === b.port;
+}
+
+function toConnection({ healthy: _healthy, capabilities: _capabilities, ...connection }: ConnectionStatus): Connection {
+  return connection;
+}
+
 function resolveBackendForModel(modelName: string, currentBackend?: BackendType): BackendType {
   if (modelName.startsWith('lmstudio:')) {
     return 'lmstudio';
@@ -336,6 +484,7 @@ function resolveBackendForModel(modelName: string, currentBackend?: BackendType)
 export async function hydratePersistedState(): Promise<void> {
   initPersistenceSubscriptions();
   initStreamingListener();
+  initRunningModelsListener();
 
   if (hasHydrated) return;
 
@@ -450,6 +599,8 @@ export async function hydratePersistedState(): Promise<void> {
   } catch (error) {
     console.error('Failed to hydrate persisted state', error);
   } finally {
+    await useConnectionStore.getState().loadConnections();
+    await useConnectionStore.getState().loadCloudProviders();
     hasHydrated = true;
     if (loadedFromFolder) {
       try {
@@ -472,9 +623,16 @@ interface ConnectionState {
   isConnecting: boolean;
   connectionError: string | null;
   setConnections: (connections: Connection[]) => void;
-  addConnection: (conn: Connection) => void;
-  removeConnection: (id: string) => void;
-  updateConnection: (id: string, updates: Partial<Connection>) => void;
+  /** Replace the list with the backend's registry, registering connections it does not know yet */
+  loadConnections: () => Promise<void>;
+  saveConnection: (conn: Connection) => Promise<void>;
+  removeConnection: (id: string) => Promise<void>;
+  setDefaultConnection: (id: string) => Promise<void>;
+  /** Cloud providers saved in the backend, each registered as a connection with the same id */
+  cloudProviders: CloudProvider[];
+  loadCloudProviders: () => Promise<void>;
+  saveCloudProvider: (provider: CloudProvider) => Promise<void>;
+  removeCloudProvider: (id: string) => Promise<void>;
   testConnection: (
     host: string,
     port: number,
@@ -482,7 +640,8 @@ interface ConnectionState {
     backend: BackendType,
     username?: string,
     password?: string,
-    apiToken?: string
+    apiToken?: string,
+    connectionId?: string
   ) => Promise<boolean>;
   checkStatus: () => Promise<boolean>;
 }
@@ -490,7 +649,7 @@ interface ConnectionState {
 export const useConnectionStore = create<ConnectionState>((set, get) => ({
   connections: [
     {
-      id: uuidv4(),
+      id: 'local-ollama',
       name: 'Local Ollama',
       backend: 'ollama',
       host: 'localhost',
@@ -507,22 +666,86 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
 
   setConnections: (connections) => set({ connections }),
 
-  addConnection: (conn) =>
-    set((state) => ({ connections: [...state.connections, conn] })),
+  loadConnections: async () => {
+    try {
+      let connections = (await invoke<ConnectionStatus[]>('list_connections')).map(toConnection);
+      // Connections configured before the backend kept its own registry
+      const missing = get().connections.filter(
+        (local) => !connections.some((conn) => conn.id === local.id || sameEndpoint(conn, local))
+      );
+      if (missing.length > 0) {
+        for (const connection of missing) {
+          await invoke<ConnectionStatus>('save_connection', { connection });
+        }
+        connections = (await invoke<ConnectionStatus[]>('list_connections')).map(toConnection);
+      }
 
-  removeConnection: (id) =>
+      set((state) => {
+        const previous = state.activeConnection;
+        return {
+          connections,
+          activeConnection:
+            (previous && connections.find((c) => c.id === previous.id || sameEndpoint(c, previous))) ??
+            connections.find((c) => c.isDefault) ??
+            null,
+        };
+      });
+    } catch (error) {
+      console.error('Failed to load connections:', error);
+    }
+  },
+
+  saveConnection: async (conn) => {
+    const saved = toConnection(await invoke<ConnectionStatus>('save_connection', { connection: conn }));
     set((state) => ({
-      connections: state.connections.filter((c) => c.id !== id),
-    })),
+      connections: state.connections.some((c) => c.id === saved.id)
+        ? state.connections.map((c) => (c.id === saved.id ? saved : c))
+        : [...state.connections, saved],
+    }));
+  },
+
+  removeConnection: async (id) => {
+    await invoke<boolean>('remove_connection', { connectionId: id });
+    set((state) => ({ connections: state.connections.filter((c) => c.id !== id) }));
+    await get().loadConnections();
+  },
 
-  updateConnection: (id, updates) =>
+  setDefaultConnection: async (id) => {
+    await invoke<void>('set_default_connection', { connectionId: id });
     set((state) => ({
-      connections: state.connections.map((c) =>
-        c.id === id ? { ...c, ...updates } : c
-      ),
-    })),
+      connections: state.connections.map((c) => ({ ...c, isDefault: c.id === id })),
+    }));
+  },
+
+  cloudProviders: [],
+
+  loadCloudProviders: async () => {
+    try {
+      const cloudProviders = await invoke<CloudProvider[]>('list_cloud_providers');
+      set({ cloudProviders });
+    } catch (error) {
+      console.error('Failed to load cloud providers:', error);
+    }
+  },
+
+  saveCloudProvider: async (provider) => {
+    await invoke<ConnectionStatus>('save_cloud_provider', { provider });
+    set((state) => ({
+      cloudProviders: state.cloudProviders.some((p) => p.id === provider.id)
+        ? state.cloudProviders.map((p) => (p.id === provider.id ? provider : p))
+        : [...state.cloudProviders, provider],
+    }));
+    await get().loadConnections();
+  },
+
+  removeCloudProvider: async (id) => {
+    await get().removeConnection(id);
+    set((state) => ({
+      cloudProviders: state.cloudProviders.filter((p) => p.id !== id),
+    }));
+  },
 
-  testConnection: async (host, port, useHttps, backend, username, password, apiToken) => {
+  testConnection: async (host, port, useHttps, backend, username, password, apiToken, connectionId) => {
     set({ isConnecting: true, connectionError: null });
     try {
       const result = await invoke<boolean>('test_connection', {
@@ -533,10 +756,13 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
         username: username ?? null,
         password: password ?? null,
         apiToken: apiToken ?? null,
+        connectionId: connectionId ?? null,
       });
 
-      const fallbackConnection = get().connections.find(
-        (c) => c.host === host && c.port === port && c.backend === backend
+      // A successful test registers the connection, so pick it up from the registry
+      if (result) await get().loadConnections();
+      const fallbackConnection = get().connections.find((c) =>
+        connectionId ? c.id === connectionId : c.host === host && c.port === port && c.backend === backend
       ) ?? get().connections[0] ?? null;
 
       set({
@@ -568,7 +794,9 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
 
   checkStatus: async () => {
     try {
-      const result = await invoke<boolean>('get_connection_status');
+      const result = await invoke<boolean>('get_connection_status', {
+        connectionId: get().activeConnection?.id ?? null,
+      });
       set({ isConnected: result });
       return result;
     } catch {
@@ -586,11 +814,22 @@ interface ModelState {
   error: string | null;
   fetchModels: () => Promise<void>;
   setSelectedModel: (model: string) => void;
-  pullModel: (name: string) => Promise<string>;
+  /** Pulls to `connectionId`, or the active connection */
+  pullModel: (name: string, connectionId?: string) => Promise<string>;
   deleteModel: (name: string) => Promise<void>;
+  /** Models loaded into memory, kept current by `running_models_changed` */
+  runningModels: RunningModel[];
+  fetchRunningModels: () => Promise<void>;
+  preloadModel: (name: string, keepAlive?: string) => Promise<void>;
+  unloadModel: (name: string) => Promise<void>;
+  /** Progress is reported through `model_create_progress` events */
+  createModel: (request: ModelCreateRequest) => Promise<string>;
+  copyModel: (source: string, destination: string) => Promise<void>;
+  renameModel: (source: string, destination: string) => Promise<void>;
+  exportModelfile: (name: string) => Promise<string>;
 }
 
-export const useModelStore = create<ModelState>((set) => ({
+export const useModelStore = create<ModelState>((set, get) => ({
   models: [],
   selectedModel: null,
   isLoading: false,
@@ -623,14 +862,66 @@ export const useModelStore = create<ModelState>((set) => ({
 
   setSelectedModel: (model) => set({ selectedModel: model }),
 
-  pullModel: async (name) => {
-    const result = await invoke<string>('pull_model', { modelName: name });
+  pullModel: async (name, connectionId) => {
+    const result = await invoke<string>('pull_model', {
+      modelName: name,
+      connectionId: connectionId ?? useConnectionStore.getState().activeConnection?.id ?? null,
+    });
     return result;
   },
 
   deleteModel: async (name) => {
-    await invoke<void>('delete_model', { modelName: name });
+    await invoke<void>('delete_model', { modelName: name, connectionId: modelConnectionId(name) });
+  },
+
+  runningModels: [],
+
+  fetchRunningModels: async () => {
+    const runningModels = await invoke<RunningModel[]>('list_running_models');
+    set({ runningModels });
+  },
+
+  preloadModel: async (name, keepAlive) => {
+    await invoke<void>('preload_model', {
+      modelName: name,
+      keepAlive: keepAlive ?? null,
+      connectionId: modelConnectionId(name),
+    });
+  },
+
+  unloadModel: async (name) => {
+    const connectionId =
+      get().runningModels.find((model) => model.name === name)?.connection_id ?? modelConnectionId(name);
+    await invoke<void>('unload_model', { modelName: name, connectionId });
+    set((state) => ({
+      runningModels: state.runningModels.filter((model) => model.name !== name),
+    }));
   },
+
+  createModel: async (request) => {
+    const result = await invoke<string>('create_model', {
+      request: {
+        ...request,
+        parameters: request.parameters ? toBackendParameters(request.parameters) : null,
+      },
+      connectionId: modelConnectionId(request.from),
+    });
+    await get().fetchModels();
+    return result;
+  },
+
+  copyModel: async (source, destination) => {
+    await invoke<void>('copy_model', { source, destination, connectionId: modelConnectionId(source) });
+    await get().fetchModels();
+  },
+
+  renameModel: async (source, destination) => {
+    await invoke<void>('rename_model', { source, destination, connectionId: modelConnectionId(source) });
+    await get().fetchModels();
+  },
+
+  exportModelfile: (name) =>
+    invoke<string>('export_modelfile', { modelName: name, connectionId: modelConnectionId(name) }),
 }));
 
 // ─── Chat Store ───
@@ -641,6 +932,8 @@ interface ChatState {
   streamingConversationIds: Set<string>;
   isConversationSending: (id: string) => boolean;
   createConversation: (modelName: string, projectId?: string) => string;
+  /** Open a backend session, e.g. one started from the OpenCode terminal, with its full history */
+  attachSession: (session: BackendSession, modelName: string) => Promise<string>;
   setActiveConversation: (id: string | null) => void;
   deleteConversation: (id: string) => void;
   sendMessage: (content: string, attachments?: File[]) => Promise<void>;
@@ -650,6 +943,10 @@ interface ChatState {
   updateSystemPrompt: (id: string, prompt: string) => void;
   stopResponse: (conversationId: string) => void;
   toggleToolCalling: (id: string) => void;
+  /** Tools models can call; conversations enable all of them unless `enabledTools` is set */
+  availableTools: ToolDefinition[];
+  fetchTools: () => Promise<void>;
+  toggleTool: (id: string, toolName: string) => void;
   getActiveConversation: () => Conversation | undefined;
   exportConversations: () => Promise<void>;
   importConversations: () => Promise<{ imported: number; skipped: number }>;
@@ -660,6 +957,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
   activeConversationId: null,
   sendingConversationIds: new Set<string>(),
   streamingConversationIds: new Set<string>(),
+  availableTools: [],
 
   isConversationSending: (id) => {
     return get().sendingConversationIds.has(id);
@@ -684,6 +982,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
       title: 'New Conversation',
       modelName,
       backendType,
+      connectionId: modelConnectionId(modelName) ?? undefined,
       backendSessionId: undefined,
       messages: [],
       createdAt: now,
@@ -701,6 +1000,43 @@ export const useChatStore = create<ChatState>((set, get) => ({
     return id;
   },
 
+  attachSession: async (session, modelName) => {
+    const backendType: BackendType = useConnectionStore.getState().activeConnection?.backend ?? 'opencode';
+    const existing = get().conversations.find((c) => c.backendSessionId === session.id);
+    const history = await getSessionMessages(session.id, backendType);
+    const messages = history.map((message) => fromBackendMessage(message, backendType));
+    const now = new Date().toISOString();
+
+    if (existing) {
+      set((s) => ({
+        conversations: s.conversations.map((c) =>
+          c.id === existing.id ? { ...c, messages, updatedAt: session.updatedAt ?? now } : c
+        ),
+        activeConversationId: existing.id,
+      }));
+      return existing.id;
+    }
+
+    const conversation: Conversation = {
+      id: uuidv4(),
+      title: session.title || 'New Conversation',
+      modelName,
+      backendType,
+      backendSessionId: session.id,
+      messages,
+      createdAt: session.createdAt ?? now,
+      updatedAt: session.updatedAt ?? now,
+      parameters: { temperature: 0.7, topK: 40, topP: 0.9 },
+      toolCallingEnabled: false,
+      restoredFromFolder: false,
+    };
+    set((s) => ({
+      conversations: [conversation, ...s.conversations],
+      activeConversationId: conversation.id,
+    }));
+    return conversation.id;
+  },
+
   setActiveConversation: (id) => set({ activeConversationId: id }),
 
   deleteConversation: (id) =>
@@ -786,23 +1122,25 @@ export const useChatStore = create<ChatState>((set, get) => ({
 
     try {
       // Build messages for API
-        const allMessages = [...conv.messages, userMessage].map((m) => ({
-          id: m.id,
-          role: m.role,
-          content: m.content,
-          timestamp: m.timestamp,
-          model_name: m.modelName ?? null,
-          backend_type: m.backendType ?? null,
-          is_error: m.isError,
-          token_count: m.tokenCount ?? null,
-          attachments: m.attachments.map((a) => ({
+      const allMessages = [...conv.messages, userMessage].map((m) => ({
+        id: m.id,
+        role: m.role,
+        content: m.content,
+        timestamp: m.timestamp,
+        model_name: m.modelName ?? null,
+        backend_type: m.backendType ?? null,
+        is_error: m.isError,
+        token_count: m.tokenCount ?? null,
+        prompt_token_count: m.promptTokenCount ?? null,
+        cost: m.cost ?? null,
+        attachments: m.attachments.map((a) => ({
           id: a.id,
           name: a.name,
           mime_type: a.mimeType,
           data: a.data,
           size: a.size,
         })),
-        tool_calls: [],
+        tool_calls: (m.toolCalls ?? []).map(toBackendToolCall),
         status: m.status,
         status_message: m.statusMessage ?? null,
       }));
@@ -811,25 +1149,69 @@ export const useChatStore = create<ChatState>((set, get) => ({
 
       const result = await invoke<{
         content: string;
+        reasoning?: string | null;
+        tool_calls?: BackendToolCall[];
+        prompt_eval_count?: number;
         eval_count?: number;
         total_duration?: number;
+        cost?: number;
         session_id?: string;
+        summary?: ConversationSummary | null;
+        summary_updated?: boolean;
+        structured?: unknown;
+        structured_error?: string | null;
       }>('send_message', {
         model: conv.modelName,
         messages: allMessages,
         systemPrompt: conv.systemPrompt ?? null,
-        parameters: {
-          temperature: conv.parameters.temperature,
-          top_k: conv.parameters.topK ?? null,
-          top_p: conv.parameters.topP ?? null,
-          max_tokens: conv.parameters.maxTokens ?? null,
-        },
-        stream: backend !== 'opencode',
+        parameters: toBackendParameters(conv.parameters),
+        stream: true,
         requestId,
         sessionId: conv.backendSessionId ?? null,
         backend,
+        connectionId: conv.connectionId ?? null,
+        conversationId: conv.id,
+        projectId: conv.projectId ?? null,
+        contextStrategy: conv.contextStrategy ?? null,
+        summarization: conv.summarization ?? null,
+        summary: conv.summary ?? null,
+        think: conv.think ?? null,
+        keepAlive: conv.keepAlive ?? null,
+        responseFormat: conv.responseFormat ?? null,
+        toolCallingEnabled: conv.toolCallingEnabled,
+        enabledTools: conv.enabledTools ?? null,
+        maxToolCalls: useSettingsStore.getState().settings.toolConfig.maxToolCalls,
       });
 
+      if (conv.responseFormat) {
+        set((s) => ({
+          conversations: s.conversations.map((c) =>
+            c.id === conv.id
+              ? {
+                  ...c,
+                  messages: c.messages.map((m) =>
+                    m.id === assistantMessageId
+                      ? {
+                          ...m,
+                          structured: result.structured ?? undefined,
+                          structuredError: result.structured_error ?? undefined,
+                        }
+                      : m
+                  ),
+                }
+              : c
+          ),
+        }));
+      }
+
+      if (result.summary_updated || (conv.summary && result.summary === null)) {
+        set((s) => ({
+          conversations: s.conversations.map((c) =>
+            c.id === conv.id ? { ...c, summary: result.summary ?? undefined } : c
+          ),
+        }));
+      }
+
       if (result.session_id) {
         set((s) => ({
           conversations: s.conversations.map((c) =>
@@ -852,8 +1234,12 @@ export const useChatStore = create<ChatState>((set, get) => ({
                        ? {
                           ...m,
                           content: result.content,
+                          reasoning: result.reasoning ?? undefined,
+                          toolCalls: (result.tool_calls ?? []).reduce(upsertToolCall, m.toolCalls),
                           status: 'sent',
                           tokenCount: result.eval_count,
+                          promptTokenCount: result.prompt_eval_count,
+                          cost: result.cost,
                         }
                       : m
                   ),
@@ -874,6 +1260,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
           const title = await invoke<string>('generate_title', {
             model: conv.modelName,
             firstMessage: content,
+            connectionId: conv.connectionId ?? null,
           });
           set((s) => ({
             conversations: s.conversations.map((c) =>
@@ -933,6 +1320,7 @@ export const useChatStore = create<ChatState>((set, get) => ({
               ...c,
               modelName: model,
               backendType: resolveBackendForModel(model, c.backendType),
+              connectionId: modelConnectionId(model) ?? (c.modelName === model ? c.connectionId : undefined),
               backendSessionId: c.modelName === model ? c.backendSessionId : undefined,
             }
           : c
@@ -956,6 +1344,10 @@ export const useChatStore = create<ChatState>((set, get) => ({
   stopResponse: (conversationId) => {
     for (const [requestId, stream] of activeStreams.entries()) {
       if (stream.conversationId === conversationId) {
+        // The backend stops the generation and returns the partial response
+        void invoke<boolean>('cancel_message', { requestId }).catch((error) => {
+          console.error('Failed to cancel message:', error);
+        });
         activeStreams.delete(requestId);
         streamsWithChunks.delete(requestId);
         break;
@@ -994,6 +1386,29 @@ export const useChatStore = create<ChatState>((set, get) => ({
       ),
     })),
 
+  fetchTools: async () => {
+    try {
+      const availableTools = await invoke<ToolDefinition[]>('list_tools');
+      set({ availableTools });
+    } catch (error) {
+      console.error('Failed to list tools:', error);
+    }
+  },
+
+  toggleTool: (id, toolName) =>
+    set((s) => ({
+      conversations: s.conversations.map((c) => {
+        if (c.id !== id) return c;
+        const enabled = c.enabledTools ?? s.availableTools.map((tool) => tool.name);
+        return {
+          ...c,
+          enabledTools: enabled.includes(toolName)
+            ? enabled.filter((name) => name !== toolName)
+            : [...enabled, toolName],
+        };
+      }),
+    })),
+
   getActiveConversation: () => {
     const state = get();
     return state.conversations.find((c) => c.id === state.activeConversationId);
@@ -1273,10 +1688,118 @@ export const useUIStore = create<UIState>((set) => ({
 }));
 
 // ─── Tool Functions ───
-export async function fetchWebpage(url: string): Promise<{ url: string; status: number; content_type: string; content: string; length: number }> {
+export async function fetchWebpage(url: string): Promise<{
+  url: string;
+  status: number;
+  content_type: string;
+  title: string | null;
+  byline: string | null;
+  canonical_url: string | null;
+  description: string | null;
+  content: string;
+  length: number;
+  truncated: boolean;
+}> {
   return invoke('fetch_webpage', { url });
 }
 
+export interface WebSearchResult {
+  rank: number;
+  title: string;
+  url: string;
+  snippet: string;
+}
+
+export async function webSearch(query: string, maxResults?: number): Promise<{
+  query: string;
+  provider: 'jina' | 'searxng';
+  results: WebSearchResult[];
+  cached: boolean;
+}> {
+  return invoke('web_search', { query, maxResults: maxResults ?? null });
+}
+
+// ─── Usage Functions ───
+export interface UsageBucket {
+  key: string;
+  requests: number;
+  promptTokens: number;
+  completionTokens: number;
+  cost: number;
+}
+
+export interface UsageBudget {
+  monthlyLimit?: number | null;
+  providerLimits?: Record<string, number>;
+}
+
+export interface UsageReport {
+  since: string | null;
+  until: string | null;
+  total: UsageBucket;
+  byModel: UsageBucket[];
+  byProvider: UsageBucket[];
+  byProject: UsageBucket[];
+  byDay: UsageBucket[];
+  budget: UsageBudget;
+  budgetStatus: { scope: string; limit: number; spent: number; exceeded: boolean }[];
+}
+
+export async function getUsageReport(since?: string, until?: string): Promise<UsageReport> {
+  return invoke('get_usage_report', { since: since ?? null, until: until ?? null });
+}
+
+export async function setUsageBudget(budget: UsageBudget): Promise<void> {
+  return invoke('set_usage_budget', { budget });
+}
+
+// ─── Backend sessions ───
+export async function listSessions(backend?: BackendType): Promise<BackendSession[]> {
+  return invoke('list_sessions', { backend: backend ?? null });
+}
+
+async function getSessionMessages(sessionId: string, backend?: BackendType): Promise<BackendMessage[]> {
+  return invoke('get_session_messages', { sessionId, backend: backend ?? null });
+}
+
+export async function abortSession(sessionId: string, backend?: BackendType): Promise<boolean> {
+  return invoke('abort_session', { sessionId, backend: backend ?? null });
+}
+
+export async function deleteSession(sessionId: string, backend?: BackendType): Promise<void> {
+  return invoke('delete_session', { sessionId, backend: backend ?? null });
+}
+
+export async function shareSession(sessionId: string, backend?: BackendType): Promise<BackendSession> {
+  return invoke('share_session', { sessionId, backend: backend ?? null });
+}
+
+export async function unshareSession(sessionId: string, backend?: BackendType): Promise<BackendSession> {
+  return invoke('unshare_session', { sessionId, backend: backend ?? null });
+}
+
+export async function summarizeSession(sessionId: string, model: string, backend?: BackendType): Promise<void> {
+  return invoke('summarize_session', { sessionId, model, backend: backend ?? null });
+}
+
+export async function respondOpencodePermission(
+  sessionId: string,
+  permissionId: string,
+  response: PermissionResponse
+): Promise<void> {
+  return invoke('respond_opencode_permission', { sessionId, permissionId, response });
+}
+
+/** Subscribe to permission requests raised by OpenCode agent runs */
+export function onOpencodePermissionRequest(
+  handler: (requestId: string, permission: PermissionRequest) => void
+): Promise<() => void> {
+  return listen<{ requestId: string; permission: PermissionRequest }>(
+    'opencode_permission_request',
+    (event) => handler(event.payload.requestId, event.payload.permission)
+  );
+}
+
 // ─── Sync Store ───
 interface SyncState {
   serverRunning: boolean;
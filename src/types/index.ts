@@ -1,6 +1,6 @@
 // Types mirroring the Rust models for the frontend
 
-export type BackendType = 'ollama' | 'opencode' | 'lmstudio';
+export type BackendType = 'ollama' | 'opencode' | 'lmstudio' | 'openai' | 'anthropic' | 'gemini';
 
 export interface Connection {
   id: string;
@@ -12,11 +12,19 @@ export interface Connection {
   username?: string;
   password?: string;
   apiToken?: string;
+  /** Full API base URL for cloud providers, used instead of host and port */
+  apiBaseUrl?: string;
   isDefault: boolean;
   createdAt: string;
   lastConnectedAt?: string;
 }
 
+/** A registered connection with its last known health, from `list_connections` */
+export interface ConnectionStatus extends Connection {
+  healthy: boolean;
+  capabilities: Record<string, boolean>;
+}
+
 export interface Message {
   id: string;
   role: 'user' | 'assistant' | 'system' | 'tool';
@@ -26,13 +34,44 @@ export interface Message {
   backendType?: BackendType;
   isError: boolean;
   tokenCount?: number;
+  promptTokenCount?: number;
+  cost?: number;
   attachments: Attachment[];
   toolCalls: ToolCall[];
   status: 'sent' | 'sending' | 'queued' | 'failed' | 'draft';
   statusMessage?: string;
   reasoning?: string;
+  /** Parsed response when the conversation requests structured output */
+  structured?: unknown;
+  /** Why the response did not parse or match the schema */
+  structuredError?: string;
+}
+
+/** A conversation held by a backend, such as an OpenCode session */
+export interface BackendSession {
+  id: string;
+  title: string;
+  createdAt: string | null;
+  updatedAt: string | null;
+  parentId: string | null;
+  shareUrl: string | null;
+  directory: string | null;
+}
+
+/** An agent asking for approval before it runs a command or edits files */
+export interface PermissionRequest {
+  id: string;
+  sessionId: string;
+  /** Kind of action, e.g. `bash` or `edit` */
+  permissionType: string;
+  patterns: string[];
+  title: string;
+  callId: string | null;
+  metadata: unknown;
 }
 
+export type PermissionResponse = 'allow' | 'deny' | 'always';
+
 export interface Attachment {
   id: string;
   name: string;
@@ -57,11 +96,20 @@ export interface ToolResult {
   summary?: string;
 }
 
+/** Tool a model can call, as returned by `list_tools` */
+export interface ToolDefinition {
+  name: string;
+  description: string;
+  parameters: Record<string, unknown>;
+}
+
 export interface Conversation {
   id: string;
   title: string;
   modelName: string;
   backendType?: BackendType;
+  /** Connection serving the model; unset falls back to the backend type's connection */
+  connectionId?: string;
   backendSessionId?: string;
   messages: Message[];
   createdAt: string;
@@ -70,21 +118,61 @@ export interface Conversation {
   parameters: ModelParameters;
   projectId?: string;
   toolCallingEnabled: boolean;
+  enabledTools?: string[];
+  contextStrategy?: ContextStrategy;
+  /** Ask thinking models to reason before answering; unset keeps the model default */
+  think?: boolean;
+  /** How long Ollama keeps the model loaded, e.g. `10m`, `-1` to keep it or `0` to unload */
+  keepAlive?: string;
+  /** Constrain responses to JSON, or to JSON matching a JSON Schema */
+  responseFormat?: ResponseFormat;
+  summarization?: SummarizationConfig;
+  summary?: ConversationSummary;
   restoredFromFolder?: boolean;
 }
 
+export type ResponseFormat = 'json' | Record<string, unknown>;
+
+export type ContextStrategy = 'sliding_window' | 'keep_first_and_last' | 'summarize';
+
+export interface SummarizationConfig {
+  enabled: boolean;
+  tokenThreshold: number;
+  model?: string;
+  connectionId?: string;
+  keepRecentMessages: number;
+}
+
+export interface ConversationSummary {
+  content: string;
+  throughMessageId: string;
+  summarizedMessageCount: number;
+  model: string;
+  updatedAt: string;
+}
+
 export interface ModelParameters {
   temperature: number;
   topK?: number;
   topP?: number;
   maxTokens?: number;
+  /** Context window to run the model with, for Ollama and LM Studio */
+  numCtx?: number;
+  seed?: number;
+  repeatPenalty?: number;
+  minP?: number;
+  stop?: string[];
+  /** 0 disables Mirostat sampling; 1 and 2 select the algorithm version */
+  mirostat?: 0 | 1 | 2;
+  /** Layers to offload to the GPU */
+  numGpu?: number;
 }
 
 export const PARAMETER_PRESETS: Record<string, ModelParameters> = {
   balanced: { temperature: 0.7, topK: 40, topP: 0.9 },
-  creative: { temperature: 1.2, topK: 80, topP: 0.95 },
-  precise: { temperature: 0.2, topK: 10, topP: 0.5 },
-  code: { temperature: 0.1, topK: 5, topP: 0.3 },
+  creative: { temperature: 1.2, topK: 80, topP: 0.95, minP: 0.05, repeatPenalty: 1.05 },
+  precise: { temperature: 0.2, topK: 10, topP: 0.5, minP: 0.1, repeatPenalty: 1.1 },
+  code: { temperature: 0.1, topK: 5, topP: 0.3, minP: 0.05, repeatPenalty: 1.0, numCtx: 8192 },
 };
 
 export interface Project {
@@ -106,6 +194,30 @@ export interface OllamaModel {
   size?: number;
   digest?: string;
   details?: OllamaModelDetails;
+  connection_id?: string;
+}
+
+/** Model to build from a base model with a baked-in prompt, template, parameters and adapter */
+export interface ModelCreateRequest {
+  name: string;
+  from: string;
+  system?: string;
+  template?: string;
+  parameters?: ModelParameters;
+  /** Path on this machine to a LoRA adapter for the base model */
+  adapter?: string;
+}
+
+/** A model loaded into memory, from Ollama's `/api/ps` */
+export interface RunningModel {
+  name: string;
+  model?: string;
+  size?: number;
+  size_vram?: number;
+  digest?: string;
+  details?: OllamaModelDetails;
+  expires_at?: string;
+  connection_id?: string;
 }
 
 export interface OllamaModelDetails {
@@ -130,6 +242,17 @@ export interface AppSettings {
 export interface ToolConfig {
   enabled: boolean;
   maxToolCalls: number;
+  webSearchEnabled?: boolean;
+  jinaApiKey?: string;
+  maxSearchResults?: number;
+  cacheSearchResults?: boolean;
+  searchProvider?: 'jina' | 'searxng';
+  /** Base URL of the search endpoint; required for SearXNG */
+  searchEndpoint?: string;
+  /** Domains web tools may fetch from; empty allows any public domain */
+  allowedDomains?: string[];
+  /** Domains web tools must never fetch from */
+  blockedDomains?: string[];
 }
 
 export interface SyncConfig {
@@ -57,6 +57,12 @@ export function getBackendLabel(backendType?: BackendType): string {
       return 'OpenCode';
     case 'ollama':
       return 'Ollama';
+    case 'openai':
+      return 'OpenAI-compatible';
+    case 'anthropic':
+      return 'Anthropic';
+    case 'gemini':
+      return 'Google Gemini';
     default:
       return 'Unknown';
   }